@@ -0,0 +1,323 @@
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{gamepad::GamepadInput, storage::ScoreStorage, tuning::GameTuning};
+
+pub const BINDINGS_KEY: &str = "input_bindings";
+
+/// What the player can ask for, whatever the device used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    /// Picks the highlighted menu item.
+    Confirm,
+    Dash,
+}
+
+/// Keys and gamepad buttons triggering an [`Action`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionBinding {
+    pub action: Action,
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<GamepadButtonType>,
+}
+
+/// Bindings of every [`Action`]. Touches are not rebindable: a finger on the
+/// screen steers the player through the virtual joystick, and menus are tapped
+/// directly.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    bindings: Vec<ActionBinding>,
+}
+
+/// Actions held this frame through their bindings, plus the analog direction
+/// asked by the left stick or the virtual joystick. Gameplay and menus only read this.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    buttons: ButtonInput<Action>,
+    pub axis: Option<Vec2>,
+    /// Whether `axis` comes from the virtual joystick, whose finger rests on
+    /// the screen for the whole run.
+    pub joystick: bool,
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .init_resource::<InputBindings>()
+            .add_systems(Startup, load_bindings)
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Pause,
+        Action::Confirm,
+        Action::Dash,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Dash => "Dash",
+        }
+    }
+}
+
+impl ActionBinding {
+    fn new(action: Action, keys: &[KeyCode], buttons: &[GamepadButtonType]) -> Self {
+        Self {
+            action,
+            keys: keys.to_vec(),
+            buttons: buttons.to_vec(),
+        }
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                ActionBinding::new(
+                    Action::MoveUp,
+                    &[KeyCode::ArrowUp, KeyCode::KeyW],
+                    &[GamepadButtonType::DPadUp],
+                ),
+                ActionBinding::new(
+                    Action::MoveDown,
+                    &[KeyCode::ArrowDown, KeyCode::KeyS],
+                    &[GamepadButtonType::DPadDown],
+                ),
+                ActionBinding::new(
+                    Action::MoveLeft,
+                    &[KeyCode::ArrowLeft, KeyCode::KeyA],
+                    &[GamepadButtonType::DPadLeft],
+                ),
+                ActionBinding::new(
+                    Action::MoveRight,
+                    &[KeyCode::ArrowRight, KeyCode::KeyD],
+                    &[GamepadButtonType::DPadRight],
+                ),
+                ActionBinding::new(
+                    Action::Pause,
+                    &[KeyCode::Escape],
+                    &[GamepadButtonType::Start],
+                ),
+                ActionBinding::new(
+                    Action::Confirm,
+                    &[KeyCode::Enter, KeyCode::Space],
+                    &[GamepadButtonType::South],
+                ),
+                ActionBinding::new(
+                    Action::Dash,
+                    &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+                    &[GamepadButtonType::East],
+                ),
+            ],
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &ActionBinding {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .expect("every action has a binding")
+    }
+
+    fn get_mut(&mut self, action: Action) -> &mut ActionBinding {
+        self.bindings
+            .iter_mut()
+            .find(|binding| binding.action == action)
+            .expect("every action has a binding")
+    }
+
+    /// Makes `key` the only key triggering `action`, keeping its gamepad buttons.
+    /// See [`InputBindings::rebind`] for the other actions bound to `key`.
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) {
+        self.rebind(action, key, |binding| &mut binding.keys);
+    }
+
+    /// Makes `button` the only gamepad button triggering `action`, keeping its keys.
+    /// See [`InputBindings::rebind`] for the other actions bound to `button`.
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) {
+        self.rebind(action, button, |binding| &mut binding.buttons);
+    }
+
+    // An input triggers a single action, so the other actions lose it. One
+    // left without any input of that kind takes the former ones of `action`,
+    // which swaps the two bindings.
+    fn rebind<T: Copy + PartialEq>(
+        &mut self,
+        action: Action,
+        input: T,
+        inputs: fn(&mut ActionBinding) -> &mut Vec<T>,
+    ) {
+        let mut previous = std::mem::replace(inputs(self.get_mut(action)), vec![input]);
+        previous.retain(|other| *other != input);
+        for binding in &mut self.bindings {
+            if binding.action == action {
+                continue;
+            }
+            let bound = inputs(binding);
+            if bound.contains(&input) {
+                bound.retain(|other| *other != input);
+                if bound.is_empty() {
+                    *bound = previous.clone();
+                }
+            }
+        }
+    }
+
+    /// Takes over the stored bindings. Actions missing from them, like the
+    /// ones added after they were stored, keep their default bindings.
+    pub fn merge(&mut self, stored: InputBindings) {
+        for binding in stored.bindings {
+            if let Some(current) = self
+                .bindings
+                .iter_mut()
+                .find(|current| current.action == binding.action)
+            {
+                *current = binding;
+            }
+        }
+    }
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.buttons.pressed(action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.buttons.just_pressed(action)
+    }
+
+    pub fn press(&mut self, action: Action) {
+        self.buttons.press(action)
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.buttons.release(action)
+    }
+
+    /// Releases `action` without it counting as just released.
+    pub fn reset(&mut self, action: Action) {
+        self.buttons.reset(action)
+    }
+
+    /// Forgets the actions pressed this frame, once something consumed the input.
+    pub fn clear(&mut self) {
+        self.buttons.clear()
+    }
+
+    /// Direction asked by the move actions or, when none is held, by the
+    /// analog input.
+    pub fn movement(&self) -> Option<Vec2> {
+        let axis = |negative, positive| {
+            self.pressed(positive) as i8 as f32 - self.pressed(negative) as i8 as f32
+        };
+        let direction = Vec2::new(
+            axis(Action::MoveLeft, Action::MoveRight),
+            axis(Action::MoveDown, Action::MoveUp),
+        );
+        if direction != Vec2::ZERO {
+            Some(direction)
+        } else {
+            self.axis
+        }
+    }
+}
+
+fn load_bindings(storage: Res<ScoreStorage>, mut bindings: ResMut<InputBindings>) {
+    match storage.get::<InputBindings>(BINDINGS_KEY) {
+        Ok(Some(stored)) => bindings.merge(stored),
+        Ok(None) => {}
+        Err(error) => warn!("ignoring stored input bindings: {error}"),
+    }
+}
+
+pub fn update_action_state(
+    mut actions: ResMut<ActionState>,
+    bindings: Res<InputBindings>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: GamepadInput,
+    tuning: Res<GameTuning>,
+) {
+    actions.clear();
+    for binding in &bindings.bindings {
+        if keyboard_input.any_pressed(binding.keys.iter().copied())
+            || binding
+                .buttons
+                .iter()
+                .any(|button| gamepad_input.pressed(*button))
+        {
+            actions.press(binding.action);
+        } else {
+            actions.release(binding.action);
+        }
+    }
+    actions.axis = gamepad_input.left_stick(tuning.gamepad_dead_zone);
+    actions.joystick = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_a_shared_key_clears_it_elsewhere() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_key(Action::MoveUp, KeyCode::ArrowDown);
+        assert_eq!(bindings.get(Action::MoveUp).keys, [KeyCode::ArrowDown]);
+        assert_eq!(bindings.get(Action::MoveDown).keys, [KeyCode::KeyS]);
+    }
+
+    #[test]
+    fn rebinding_the_last_key_of_an_action_swaps_them() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_key(Action::Pause, KeyCode::KeyP);
+        bindings.bind_key(Action::Dash, KeyCode::KeyP);
+        assert_eq!(bindings.get(Action::Dash).keys, [KeyCode::KeyP]);
+        assert_eq!(
+            bindings.get(Action::Pause).keys,
+            [KeyCode::ShiftLeft, KeyCode::ShiftRight]
+        );
+
+        bindings.bind_button(Action::Dash, GamepadButtonType::South);
+        assert_eq!(
+            bindings.get(Action::Dash).buttons,
+            [GamepadButtonType::South]
+        );
+        assert_eq!(
+            bindings.get(Action::Confirm).buttons,
+            [GamepadButtonType::East]
+        );
+    }
+
+    #[test]
+    fn binding_an_action_to_its_own_key_changes_nothing_else() {
+        let mut bindings = InputBindings::default();
+        bindings.bind_key(Action::Confirm, KeyCode::Space);
+        let mut expected = InputBindings::default();
+        expected.get_mut(Action::Confirm).keys = vec![KeyCode::Space];
+        assert_eq!(bindings, expected);
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_rapier2d::dynamics::Velocity;
+use rand::distributions::{Distribution, WeightedIndex};
+use serde::Deserialize;
+
+use crate::{
+    config::GameConfig, fireball::spawn_fireball_entity, graphics::SceneAssets, lifetime::FadeOut,
+    pickup::Slowed, player::Player, rng::GameRng, schedule::InGameSet,
+    screen_bound_collision_detection::ScreenCollisionEvent, tuning::GameTuning,
+};
+
+// Angle between the two halves of a split fireball and its former direction
+const SPLIT_ANGLE: f32 = 0.4;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum FireballArchetype {
+    /// Bounces off the window edges in a straight line.
+    Bouncer,
+    /// Steers toward the player.
+    Homing,
+    /// Breaks into two fast fireballs after a few bounces.
+    Splitter,
+    Large,
+    Fast,
+}
+
+/// Balance values of an archetype, on top of its behavior components. Part of
+/// [`GameTuning`], the spawner draws archetypes from them.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ArchetypeTuning {
+    pub archetype: FireballArchetype,
+    /// Applied to the current fireball speed of the difficulty curve.
+    pub speed_scale: f32,
+    /// Applied to `GameTuning::fireball_scale`.
+    pub size_scale: f32,
+    /// Chance to be spawned is `base_weight + weight_per_step * step`, where
+    /// `step` is the current step of the difficulty curve.
+    pub base_weight: f32,
+    pub weight_per_step: f32,
+}
+
+/// Turns toward the player at up to `turn_rate` radians per second.
+#[derive(Component)]
+pub struct Homing {
+    pub turn_rate: f32,
+}
+
+/// Splits when `bounces_left` wall bounces have been made.
+#[derive(Component)]
+pub struct Splitter {
+    pub bounces_left: u32,
+}
+
+pub struct ArchetypePlugin;
+
+impl Plugin for ArchetypePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            steer_homing_fireballs.in_set(InGameSet::EntityUpdates),
+        )
+        .add_systems(
+            Update,
+            split_fireballs.in_set(InGameSet::CollisionDetection),
+        );
+    }
+}
+
+impl ArchetypeTuning {
+    /// Archetypes of the default tuning. Only bouncers at first, the other
+    /// archetypes show up as the game gets harder.
+    pub fn defaults() -> Vec<Self> {
+        let archetype = |archetype, speed_scale, size_scale, base_weight, weight_per_step| Self {
+            archetype,
+            speed_scale,
+            size_scale,
+            base_weight,
+            weight_per_step,
+        };
+        vec![
+            archetype(FireballArchetype::Bouncer, 1.0, 1.0, 10.0, 0.0),
+            archetype(FireballArchetype::Large, 0.6, 2.0, 0.0, 1.0),
+            archetype(FireballArchetype::Fast, 1.6, 0.6, 0.0, 1.0),
+            archetype(FireballArchetype::Homing, 0.8, 1.0, 0.0, 0.75),
+            archetype(FireballArchetype::Splitter, 0.9, 1.2, 0.0, 0.75),
+        ]
+    }
+
+    fn weight(&self, step: u32) -> f32 {
+        (self.base_weight + self.weight_per_step * step as f32).max(0.0)
+    }
+}
+
+/// Draws the archetype of the next fireball, following the weights at `step`.
+pub fn choose_archetype(
+    archetypes: &[ArchetypeTuning],
+    rng: &mut GameRng,
+    step: u32,
+) -> FireballArchetype {
+    let weights = archetypes.iter().map(|archetype| archetype.weight(step));
+    match WeightedIndex::new(weights) {
+        Ok(index) => archetypes[index.sample(rng)].archetype,
+        Err(_) => FireballArchetype::Bouncer,
+    }
+}
+
+impl FireballArchetype {
+    pub const ALL: [FireballArchetype; 5] = [
+        FireballArchetype::Bouncer,
+        FireballArchetype::Homing,
+        FireballArchetype::Splitter,
+        FireballArchetype::Large,
+        FireballArchetype::Fast,
+    ];
+
+    pub fn color(&self) -> Color {
+        match self {
+            FireballArchetype::Bouncer => Color::WHITE,
+            FireballArchetype::Homing => Color::rgb(1.0, 0.5, 1.0),
+            FireballArchetype::Splitter => Color::rgb(0.6, 1.0, 0.6),
+            FireballArchetype::Large => Color::rgb(1.0, 0.7, 0.5),
+            FireballArchetype::Fast => Color::rgb(1.0, 1.0, 0.5),
+        }
+    }
+
+    /// Adds the components driving the behavior of this archetype.
+    pub fn insert_behavior(&self, entity: &mut EntityCommands, tuning: &GameTuning) {
+        match self {
+            FireballArchetype::Homing => {
+                entity.insert(Homing {
+                    turn_rate: tuning.homing_turn_rate,
+                });
+            }
+            FireballArchetype::Splitter => {
+                entity.insert(Splitter {
+                    bounces_left: tuning.splitter_bounces,
+                });
+            }
+            FireballArchetype::Bouncer | FireballArchetype::Large | FireballArchetype::Fast => {}
+        }
+    }
+}
+
+fn steer_homing_fireballs(
+    mut query: Query<(&Homing, &Transform, &mut Velocity)>,
+    player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for (homing, transform, mut velocity) in query.iter_mut() {
+        let to_player = player_transform.translation.xy() - transform.translation.xy();
+        if to_player == Vec2::ZERO || velocity.linvel == Vec2::ZERO {
+            continue;
+        }
+        let max_turn = homing.turn_rate * time.delta_seconds();
+        let turn = velocity
+            .linvel
+            .angle_between(to_player)
+            .clamp(-max_turn, max_turn);
+        velocity.linvel = Vec2::from_angle(turn).rotate(velocity.linvel);
+    }
+}
+
+fn split_fireballs(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<ScreenCollisionEvent>,
+    mut query: Query<(&mut Splitter, &Transform, &Velocity, Option<&Slowed>), Without<FadeOut>>,
+    scene_assets: Res<SceneAssets>,
+    tuning: Res<GameTuning>,
+    config: Res<GameConfig>,
+) {
+    for &ScreenCollisionEvent { entity } in collision_event_reader.read() {
+        let Ok((mut splitter, transform, velocity, slowed)) = query.get_mut(entity) else {
+            continue;
+        };
+        // A bounce in a corner touches two walls at once
+        if splitter.bounces_left == 0 {
+            continue;
+        }
+        splitter.bounces_left -= 1;
+        if splitter.bounces_left > 0 {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        for angle in [-SPLIT_ANGLE, SPLIT_ANGLE] {
+            let fireball = spawn_fireball_entity(
+                &mut commands,
+                &scene_assets,
+                &tuning,
+                config.fireball_collisions,
+                FireballArchetype::Fast,
+                transform.translation,
+                Vec2::from_angle(angle).rotate(velocity.linvel),
+            );
+            // The halves are already as slow as their parent
+            if let Some(slowed) = slowed {
+                commands.entity(fireball).insert(Slowed {
+                    factor: slowed.factor,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(archetypes: &[ArchetypeTuning], seed: u64, step: u32) -> Vec<FireballArchetype> {
+        let mut rng = GameRng::new(seed);
+        (0..4500)
+            .map(|_| choose_archetype(archetypes, &mut rng, step))
+            .collect()
+    }
+
+    #[test]
+    fn only_bouncers_at_first() {
+        let archetypes = ArchetypeTuning::defaults();
+        assert!(draw(&archetypes, 1, 0)
+            .iter()
+            .all(|archetype| *archetype == FireballArchetype::Bouncer));
+    }
+
+    #[test]
+    fn draws_follow_the_weights() {
+        let archetypes = ArchetypeTuning::defaults();
+        let step = 10;
+        let draws = draw(&archetypes, 3, step);
+        assert_eq!(draw(&archetypes, 3, step), draws);
+        let total: f32 = archetypes
+            .iter()
+            .map(|archetype| archetype.weight(step))
+            .sum();
+        for archetype in &archetypes {
+            let count = draws
+                .iter()
+                .filter(|drawn| **drawn == archetype.archetype)
+                .count() as f32;
+            let expected = draws.len() as f32 * archetype.weight(step) / total;
+            assert!(
+                (count - expected).abs() < expected * 0.15,
+                "{:?} drawn {count} times, expected about {expected}",
+                archetype.archetype
+            );
+        }
+    }
+}
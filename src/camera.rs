@@ -1,8 +1,7 @@
 use bevy::{prelude::*, window::WindowResized};
 
-use crate::graphics::SceneAssets;
+use crate::{graphics::SceneAssets, tuning::GameTuning};
 
-pub const BACKGROUND_SCALE: f32 = 3.1;
 const CAMERA_DISTANCE: f32 = 80.0;
 
 #[derive(Component)]
@@ -24,13 +23,17 @@ pub fn spawn_camera(mut commands: Commands) {
     });
 }
 
-pub fn spawn_background(mut commands: Commands, scene_assets: Res<SceneAssets>) {
+pub fn spawn_background(
+    mut commands: Commands,
+    scene_assets: Res<SceneAssets>,
+    tuning: Res<GameTuning>,
+) {
     commands.spawn((
         SpriteBundle {
             texture: scene_assets.background.image.clone(),
             transform: Transform {
                 translation: Vec3::new(0.0, 0.0, -1.0),
-                scale: Vec2::new(BACKGROUND_SCALE, 0.0).xxy(),
+                scale: Vec2::new(tuning.background_scale, 0.0).xxy(),
                 ..default()
             },
             ..default()
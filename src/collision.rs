@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const PLAYER_GROUP: Group = Group::GROUP_1;
+pub const FIREBALL_GROUP: Group = Group::GROUP_2;
+pub const WALL_GROUP: Group = Group::GROUP_3;
+pub const PICKUP_GROUP: Group = Group::GROUP_4;
+
+/// Kills the player on contact.
+#[derive(Component)]
+pub struct Hazard;
+
+/// How fireballs react when they meet each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FireballCollisionMode {
+    /// Fireballs go through each other.
+    PassThrough,
+    /// Fireballs bounce off each other elastically.
+    #[default]
+    Bounce,
+}
+
+impl FireballCollisionMode {
+    /// Case-insensitive, accepts `pass-through` and `bounce`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pass-through" | "passthrough" => Some(FireballCollisionMode::PassThrough),
+            "bounce" => Some(FireballCollisionMode::Bounce),
+            _ => None,
+        }
+    }
+}
+
+pub fn player_groups() -> CollisionGroups {
+    CollisionGroups::new(PLAYER_GROUP, FIREBALL_GROUP | WALL_GROUP | PICKUP_GROUP)
+}
+
+/// Groups of the player while it recovers from a hit: fireballs go through it.
+pub fn invulnerable_player_groups() -> CollisionGroups {
+    CollisionGroups::new(PLAYER_GROUP, WALL_GROUP | PICKUP_GROUP)
+}
+
+pub fn fireball_groups(mode: FireballCollisionMode) -> CollisionGroups {
+    let filters = match mode {
+        FireballCollisionMode::PassThrough => PLAYER_GROUP | WALL_GROUP,
+        FireballCollisionMode::Bounce => PLAYER_GROUP | WALL_GROUP | FIREBALL_GROUP,
+    };
+    CollisionGroups::new(FIREBALL_GROUP, filters)
+}
+
+pub fn wall_groups() -> CollisionGroups {
+    CollisionGroups::new(WALL_GROUP, PLAYER_GROUP | FIREBALL_GROUP)
+}
+
+pub fn pickup_groups() -> CollisionGroups {
+    CollisionGroups::new(PICKUP_GROUP, PLAYER_GROUP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same test as rapier: each side has to accept the other
+    fn interacts(a: CollisionGroups, b: CollisionGroups) -> bool {
+        a.memberships.intersects(b.filters) && b.memberships.intersects(a.filters)
+    }
+
+    #[test]
+    fn fireballs_meet_each_other_only_when_bouncing() {
+        let pass_through = fireball_groups(FireballCollisionMode::PassThrough);
+        let bounce = fireball_groups(FireballCollisionMode::Bounce);
+        assert!(!interacts(pass_through, pass_through));
+        assert!(interacts(bounce, bounce));
+        for fireball in [pass_through, bounce] {
+            assert!(interacts(fireball, player_groups()));
+            assert!(interacts(fireball, wall_groups()));
+            assert!(!interacts(fireball, pickup_groups()));
+            assert!(!interacts(fireball, invulnerable_player_groups()));
+        }
+    }
+
+    #[test]
+    fn pickups_only_meet_the_player() {
+        for player in [player_groups(), invulnerable_player_groups()] {
+            assert!(interacts(pickup_groups(), player));
+            assert!(interacts(wall_groups(), player));
+        }
+        assert!(!interacts(pickup_groups(), wall_groups()));
+        assert!(!interacts(pickup_groups(), pickup_groups()));
+    }
+
+    #[test]
+    fn modes_are_parsed_from_their_names() {
+        assert_eq!(
+            FireballCollisionMode::from_name("Pass-Through"),
+            Some(FireballCollisionMode::PassThrough)
+        );
+        assert_eq!(
+            FireballCollisionMode::from_name("bounce"),
+            Some(FireballCollisionMode::Bounce)
+        );
+        assert_eq!(FireballCollisionMode::from_name("sticky"), None);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    collision::FireballCollisionMode,
+    difficulty::DifficultyPreset,
+    player::MovementMode,
+    replay::{Replay, ReplayMode},
+    tuning::GameTuning,
+};
+
+/// Options used by [`crate::build_game_app`] to assemble the game.
+#[derive(Resource, Clone, Debug)]
+pub struct GameConfig {
+    /// Runs on top of `MinimalPlugins` with a fake primary window, no render and no audio.
+    pub headless: bool,
+    /// When set, every frame advances the clock by exactly this amount.
+    pub fixed_delta: Option<Duration>,
+    pub window_resolution: Vec2,
+    /// Seed of the first run; a random one is picked when missing.
+    pub seed: Option<u64>,
+    pub replay: ReplayMode,
+    /// Keeps the scores as files in this directory instead of the platform's
+    /// default store. Headless apps keep them in memory when missing.
+    pub score_dir: Option<PathBuf>,
+    /// Balance values to start with; the windowed game then follows the tuning file.
+    pub tuning: GameTuning,
+    pub difficulty: DifficultyPreset,
+    pub fireball_collisions: FireballCollisionMode,
+    pub movement: MovementMode,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        let tuning = GameTuning::default();
+        Self {
+            headless: false,
+            fixed_delta: None,
+            window_resolution: Vec2::new(
+                448. * tuning.background_scale,
+                298. * tuning.background_scale,
+            ),
+            seed: None,
+            replay: ReplayMode::Off,
+            score_dir: None,
+            tuning,
+            difficulty: DifficultyPreset::default(),
+            fireball_collisions: FireballCollisionMode::default(),
+            movement: MovementMode::default(),
+        }
+    }
+}
+
+impl GameConfig {
+    /// Reads the options passed on the command line: `--seed <u64>`,
+    /// `--difficulty <preset>`, `--fireball-collisions <pass-through|bounce>`,
+    /// `--movement <analog|8-way>`, `--record <file>` and `--replay <file>`.
+    /// Fails with a message for the player on a missing or bad value.
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse_args(std::env::args().skip(1))
+    }
+
+    /// [`GameConfig::from_args`] on the given arguments, without the program name.
+    pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value after `{arg}`"))?;
+            let bad_value = || format!("bad value `{value}` for `{arg}`");
+            if arg == "--seed" {
+                config.seed = Some(value.parse().map_err(|_| bad_value())?);
+            } else if arg == "--difficulty" {
+                config.difficulty = DifficultyPreset::from_name(&value).ok_or_else(bad_value)?;
+            } else if arg == "--fireball-collisions" {
+                config.fireball_collisions =
+                    FireballCollisionMode::from_name(&value).ok_or_else(bad_value)?;
+            } else if arg == "--movement" {
+                config.movement = MovementMode::from_name(&value).ok_or_else(bad_value)?;
+            } else if arg == "--record" {
+                config.replay = ReplayMode::Record(value.into());
+            } else if arg == "--replay" {
+                let replay = Replay::load(&value)
+                    .map_err(|error| format!("failed to load replay `{value}`: {error}"))?;
+                config.replay = ReplayMode::Play(replay);
+            } else {
+                return Err(format!("unknown option `{arg}`"));
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn headless() -> Self {
+        Self {
+            headless: true,
+            fixed_delta: Some(Duration::from_secs_f64(1.0 / 60.0)),
+            ..default()
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    actions::{Action, ActionState},
+    collision::{invulnerable_player_groups, player_groups},
+    player::{player_controller, Invulnerable, Player, PLAYER_PIXELS},
+    schedule::InGameSet,
+    state::{OnRunEnd, OnRunStart},
+    stats::RunStats,
+    tuning::GameTuning,
+};
+
+const DASH_TINT: Color = Color::rgb(0.5, 0.85, 1.0);
+// Seconds between two afterimages left behind by a dash, and their lifetime
+const AFTERIMAGE_INTERVAL: f32 = 0.04;
+const AFTERIMAGE_LIFETIME: f32 = 0.25;
+const AFTERIMAGE_ALPHA: f32 = 0.6;
+const RING_RADIUS: f32 = PLAYER_PIXELS * 0.75;
+const RING_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+const RING_READY_COLOR: Color = Color::rgba(0.5, 0.85, 1.0, 0.8);
+
+/// Set while the player dashes: it moves at `dash_speed` and hazards go
+/// through it.
+#[derive(Component)]
+pub struct Dashing {
+    timer: Timer,
+    afterimage_timer: Timer,
+}
+
+/// Fading copy of the player sprite left behind by a dash.
+#[derive(Component)]
+struct Afterimage {
+    timer: Timer,
+}
+
+/// Time left before the player can dash again.
+#[derive(Resource, Default)]
+pub struct DashCooldown {
+    pub timer: Timer,
+}
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DashCooldown>()
+            .add_systems(OnRunStart, reset_dash_cooldown)
+            .add_systems(OnRunEnd, despawn_afterimages)
+            .add_systems(
+                Update,
+                start_dash
+                    .after(player_controller)
+                    .in_set(InGameSet::UserInput),
+            )
+            .add_systems(
+                Update,
+                (spawn_afterimages, update_dash, fade_afterimages)
+                    .chain()
+                    .in_set(InGameSet::EntityUpdates),
+            );
+        // The ring is drawn with gizmos, which only exist with a renderer
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_dash_cooldown);
+        }
+    }
+}
+
+impl DashCooldown {
+    /// Before the first dash of a run the timer is empty, which counts as ready.
+    pub fn ready(&self) -> bool {
+        self.timer.fraction() >= 1.0
+    }
+}
+
+fn reset_dash_cooldown(mut cooldown: ResMut<DashCooldown>) {
+    *cooldown = DashCooldown::default();
+}
+
+fn start_dash(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    mut cooldown: ResMut<DashCooldown>,
+    mut query: Query<(Entity, &mut Velocity, &mut Sprite, Has<Dashing>), With<Player>>,
+    mut run_stats: ResMut<RunStats>,
+    tuning: Res<GameTuning>,
+) {
+    if !actions.just_pressed(Action::Dash) || !cooldown.ready() {
+        return;
+    }
+    let Ok((entity, mut velocity, mut sprite, dashing)) = query.get_single_mut() else {
+        return;
+    };
+    if dashing {
+        return;
+    }
+    // Dashes along the current heading of the player
+    let direction = velocity.linvel.try_normalize().unwrap_or(Vec2::NEG_Y);
+    velocity.linvel = direction * tuning.dash_speed;
+    sprite.color = DASH_TINT;
+    commands.entity(entity).insert((
+        Dashing {
+            timer: Timer::from_seconds(tuning.dash_duration, TimerMode::Once),
+            afterimage_timer: Timer::from_seconds(AFTERIMAGE_INTERVAL, TimerMode::Repeating),
+        },
+        invulnerable_player_groups(),
+    ));
+    cooldown.timer = Timer::from_seconds(tuning.dash_cooldown, TimerMode::Once);
+    run_stats.dashes += 1;
+}
+
+fn spawn_afterimages(
+    mut commands: Commands,
+    mut query: Query<(&mut Dashing, &Transform, &Handle<Image>, &TextureAtlas)>,
+    time: Res<Time>,
+) {
+    for (mut dashing, transform, image, atlas) in query.iter_mut() {
+        dashing.afterimage_timer.tick(time.delta());
+        for _ in 0..dashing.afterimage_timer.times_finished_this_tick() {
+            commands.spawn((
+                SpriteSheetBundle {
+                    sprite: Sprite {
+                        color: DASH_TINT.with_a(AFTERIMAGE_ALPHA),
+                        ..default()
+                    },
+                    // Behind the player
+                    transform: transform.with_translation(transform.translation - Vec3::Z * 0.1),
+                    texture: image.clone(),
+                    atlas: atlas.clone(),
+                    ..default()
+                },
+                Afterimage {
+                    timer: Timer::from_seconds(AFTERIMAGE_LIFETIME, TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+fn update_dash(
+    mut commands: Commands,
+    mut cooldown: ResMut<DashCooldown>,
+    mut query: Query<(Entity, &mut Dashing, &mut Velocity, &mut Sprite)>,
+    invulnerable_query: Query<(), With<Invulnerable>>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
+) {
+    cooldown.timer.tick(time.delta());
+    for (entity, mut dashing, mut velocity, mut sprite) in query.iter_mut() {
+        dashing.timer.tick(time.delta());
+        if !dashing.timer.finished() {
+            continue;
+        }
+        sprite.color = Color::WHITE;
+        // Back from the burst to the usual speed
+        velocity.linvel = velocity.linvel.normalize_or_zero() * tuning.initial_velocity;
+        let mut player = commands.entity(entity);
+        player.remove::<Dashing>();
+        // A hit during the dash keeps the player invulnerable for longer
+        if !invulnerable_query.contains(entity) {
+            player.insert(player_groups());
+        }
+    }
+}
+
+fn fade_afterimages(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Afterimage, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut afterimage, mut sprite) in query.iter_mut() {
+        afterimage.timer.tick(time.delta());
+        if afterimage.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            sprite
+                .color
+                .set_a(AFTERIMAGE_ALPHA * afterimage.timer.fraction_remaining());
+        }
+    }
+}
+
+fn despawn_afterimages(mut commands: Commands, query: Query<Entity, With<Afterimage>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// The ring fills up clockwise from the top while the dash recharges
+fn draw_dash_cooldown(
+    mut gizmos: Gizmos,
+    cooldown: Res<DashCooldown>,
+    query: Query<&Transform, With<Player>>,
+) {
+    let Ok(transform) = query.get_single() else {
+        return;
+    };
+    let position = transform.translation.xy();
+    if cooldown.ready() {
+        gizmos.circle_2d(position, RING_RADIUS, RING_READY_COLOR);
+    } else {
+        let fraction = cooldown.timer.fraction();
+        gizmos.circle_2d(position, RING_RADIUS, RING_COLOR);
+        gizmos.arc_2d(
+            position,
+            fraction * PI,
+            fraction * TAU,
+            RING_RADIUS,
+            RING_READY_COLOR.with_a(0.4),
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig, fireball::Fireball, schedule::InGameSet, state::OnRunStart,
+    stats::RunClock, tuning::GameTuning, ui::GameData,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DifficultyPreset {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+/// Preset the next run is played with.
+#[derive(Resource, Default)]
+pub struct Difficulty {
+    pub preset: DifficultyPreset,
+}
+
+/// How the game gets harder during a run. A new step is reached every
+/// `balls_per_step` fireballs and every `seconds_per_step` seconds survived.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DifficultyCurve {
+    pub balls_per_step: u64,
+    pub seconds_per_step: f32,
+    pub initial_fireball_speed: f32,
+    /// Applied to the fireball speed at each step.
+    pub speed_multiplier: f32,
+    pub speed_cap: f32,
+    pub initial_spawn_interval: f64,
+    /// Applied to the spawn interval at each step.
+    pub spawn_interval_factor: f64,
+    pub min_spawn_interval: f64,
+}
+
+/// What the curve prescribes at a given step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DifficultyLevel {
+    pub step: u32,
+    pub fireball_speed: f32,
+    pub spawn_interval: f64,
+}
+
+/// Step the current run has reached. Each step is applied exactly once.
+#[derive(Resource, Default)]
+pub struct DifficultyProgress {
+    pub step: u32,
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        let preset = app
+            .world
+            .get_resource::<GameConfig>()
+            .map(|config| config.difficulty)
+            .unwrap_or_default();
+        app.insert_resource(Difficulty { preset })
+            .init_resource::<DifficultyProgress>()
+            .add_systems(OnRunStart, start_difficulty)
+            .add_systems(Update, apply_difficulty.in_set(InGameSet::EntityUpdates));
+    }
+}
+
+impl DifficultyPreset {
+    pub const ALL: [DifficultyPreset; 4] = [
+        DifficultyPreset::Easy,
+        DifficultyPreset::Normal,
+        DifficultyPreset::Hard,
+        DifficultyPreset::Insane,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DifficultyPreset::Easy => "Easy",
+            DifficultyPreset::Normal => "Normal",
+            DifficultyPreset::Hard => "Hard",
+            DifficultyPreset::Insane => "Insane",
+        }
+    }
+
+    /// Case-insensitive inverse of [`DifficultyPreset::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|preset| preset == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Builds the curve of this preset around the tuned Normal values.
+    pub fn curve(&self, tuning: &GameTuning) -> DifficultyCurve {
+        // (balls per step, seconds per step, speed scale, multiplier scale,
+        //  speed cap in initial speeds, spawn interval scale, spawn interval factor)
+        let (
+            balls_per_step,
+            seconds_per_step,
+            speed_scale,
+            multiplier_scale,
+            cap,
+            spawn_scale,
+            factor,
+        ) = match self {
+            DifficultyPreset::Easy => (12, 90.0, 0.8, 0.6, 4.0, 1.25, 0.97),
+            DifficultyPreset::Normal => (10, 75.0, 1.0, 1.0, 6.0, 1.0, 0.95),
+            DifficultyPreset::Hard => (8, 60.0, 1.2, 1.2, 8.0, 0.8, 0.92),
+            DifficultyPreset::Insane => (5, 45.0, 1.5, 1.5, 10.0, 0.6, 0.9),
+        };
+        let initial_fireball_speed = tuning.initial_fireball_speed * speed_scale;
+        let initial_spawn_interval = tuning.fireball_spawn_time * spawn_scale;
+        DifficultyCurve {
+            balls_per_step,
+            seconds_per_step,
+            initial_fireball_speed,
+            speed_multiplier: 1.0 + (tuning.speed_multiplier - 1.0) * multiplier_scale,
+            speed_cap: initial_fireball_speed * cap,
+            initial_spawn_interval,
+            spawn_interval_factor: factor,
+            min_spawn_interval: initial_spawn_interval * 0.2,
+        }
+    }
+}
+
+impl DifficultyCurve {
+    pub fn level(&self, seconds_survived: f32, n_balls: u64) -> DifficultyLevel {
+        let step = (n_balls / self.balls_per_step) as u32
+            + (seconds_survived / self.seconds_per_step) as u32;
+        DifficultyLevel {
+            step,
+            fireball_speed: (self.initial_fireball_speed * self.speed_multiplier.powi(step as i32))
+                .min(self.speed_cap),
+            spawn_interval: (self.initial_spawn_interval
+                * self.spawn_interval_factor.powi(step as i32))
+            .max(self.min_spawn_interval),
+        }
+    }
+}
+
+fn start_difficulty(
+    mut progress: ResMut<DifficultyProgress>,
+    mut game_data: ResMut<GameData>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+) {
+    let level = difficulty.preset.curve(&tuning).level(0.0, 0);
+    progress.step = level.step;
+    game_data.current_fireballs_speed = level.fireball_speed;
+    // Fireballs are spawned once per fixed tick
+    fixed_time.set_timestep_seconds(level.spawn_interval);
+}
+
+fn apply_difficulty(
+    mut progress: ResMut<DifficultyProgress>,
+    mut game_data: ResMut<GameData>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut query: Query<&mut Velocity, With<Fireball>>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    clock: Res<RunClock>,
+) {
+    let level = difficulty
+        .preset
+        .curve(&tuning)
+        .level(clock.elapsed().as_secs_f32(), game_data.n_balls);
+    // Edited tuning applies right away, new steps apply once
+    if level.step <= progress.step && !tuning.is_changed() {
+        return;
+    }
+    progress.step = level.step;
+    let speed_ratio = level.fireball_speed / game_data.current_fireballs_speed;
+    game_data.current_fireballs_speed = level.fireball_speed;
+    query
+        .par_iter_mut()
+        .for_each(|mut ball_velocity| ball_velocity.linvel *= speed_ratio);
+    fixed_time.set_timestep_seconds(level.spawn_interval);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normal_curve() -> DifficultyCurve {
+        DifficultyPreset::Normal.curve(&GameTuning::default())
+    }
+
+    #[test]
+    fn each_threshold_adds_one_step() {
+        let curve = normal_curve();
+        let balls = curve.balls_per_step;
+        let seconds = curve.seconds_per_step;
+        assert_eq!(curve.level(0.0, 0).step, 0);
+        assert_eq!(curve.level(0.0, balls - 1).step, 0);
+        assert_eq!(curve.level(0.0, balls).step, 1);
+        assert_eq!(curve.level(0.0, 2 * balls - 1).step, 1);
+        assert_eq!(curve.level(seconds - 0.01, 0).step, 0);
+        assert_eq!(curve.level(seconds, 0).step, 1);
+        assert_eq!(curve.level(seconds, balls).step, 2);
+    }
+
+    #[test]
+    fn speed_and_spawn_interval_are_clamped() {
+        let curve = normal_curve();
+        let first = curve.level(0.0, 0);
+        assert_eq!(first.fireball_speed, curve.initial_fireball_speed);
+        assert_eq!(first.spawn_interval, curve.initial_spawn_interval);
+        let last = curve.level(1.0e6, 1_000_000);
+        assert_eq!(last.fireball_speed, curve.speed_cap);
+        assert_eq!(last.spawn_interval, curve.min_spawn_interval);
+    }
+
+    #[test]
+    fn presets_only_get_harder() {
+        let tuning = GameTuning::default();
+        for preset in DifficultyPreset::ALL {
+            let curve = preset.curve(&tuning);
+            let mut previous = curve.level(0.0, 0);
+            for n_balls in 1..(curve.balls_per_step * 100) {
+                let level = curve.level(0.0, n_balls);
+                assert!(level.step >= previous.step, "{preset:?}");
+                assert!(
+                    level.fireball_speed >= previous.fireball_speed,
+                    "{preset:?}"
+                );
+                assert!(
+                    level.spawn_interval <= previous.spawn_interval,
+                    "{preset:?}"
+                );
+                previous = level;
+            }
+        }
+        // And each preset starts harder than the previous one
+        for presets in DifficultyPreset::ALL.windows(2) {
+            let easier = presets[0].curve(&tuning).level(0.0, 0);
+            let harder = presets[1].curve(&tuning).level(0.0, 0);
+            assert!(harder.fireball_speed > easier.fireball_speed);
+            assert!(harder.spawn_interval < easier.spawn_interval);
+        }
+    }
+
+    #[test]
+    fn fireballs_speed_up_once_per_step() {
+        let curve = normal_curve();
+        let mut app = App::new();
+        app.add_plugins(TaskPoolPlugin::default())
+            .init_resource::<GameTuning>()
+            .init_resource::<Difficulty>()
+            .init_resource::<DifficultyProgress>()
+            .init_resource::<RunClock>()
+            .init_resource::<Time<Fixed>>()
+            .insert_resource(GameData {
+                current_fireballs_speed: curve.initial_fireball_speed,
+                ..default()
+            })
+            .add_systems(Update, apply_difficulty);
+        let initial_velocity = Vec2::new(curve.initial_fireball_speed, 0.0);
+        let fireball = app
+            .world
+            .spawn((Fireball, Velocity::linear(initial_velocity)))
+            .id();
+        let velocity = |app: &App| app.world.get::<Velocity>(fireball).unwrap().linvel;
+
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(velocity(&app), initial_velocity);
+
+        app.world.resource_mut::<GameData>().n_balls = curve.balls_per_step;
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(app.world.resource::<DifficultyProgress>().step, 1);
+        let expected = initial_velocity * curve.speed_multiplier;
+        assert!(
+            (velocity(&app) - expected).length() < 1e-3,
+            "{}",
+            velocity(&app)
+        );
+        let timestep = app.world.resource::<Time<Fixed>>().timestep().as_secs_f64();
+        let spawn_interval = curve.level(0.0, curve.balls_per_step).spawn_interval;
+        assert!((timestep - spawn_interval).abs() < 1e-6, "{timestep}");
+    }
+}
@@ -1,14 +1,22 @@
 use bevy::prelude::*;
 
 use crate::{
-    graphics::{AnimationTimer, SceneAssets},
+    graphics::{AnimationIndices, AnimationTimer, SceneAssets},
     player::{despawn_player, Player},
-    state::GameState,
+    schedule::InGameSet,
+    state::{GameState, OnRunEnd},
 };
 
+// Size of a mini explosion relative to the one ending the run
+const MINI_EXPLOSION_SCALE: f32 = 0.4;
+
 #[derive(Component)]
 struct Explosion;
 
+/// Small explosion that plays during a run, e.g. where a bomb destroyed a fireball.
+#[derive(Component)]
+struct MiniExplosion;
+
 #[derive(Event)]
 pub struct ExplosionEndedEvent;
 
@@ -24,7 +32,12 @@ impl Plugin for ExplosionPlugin {
             .add_systems(
                 Update,
                 animate_explosion.run_if(in_state(GameState::GameOver)),
-            );
+            )
+            .add_systems(
+                Update,
+                animate_mini_explosions.in_set(InGameSet::EntityUpdates),
+            )
+            .add_systems(OnRunEnd, despawn_mini_explosions);
     }
 }
 
@@ -54,7 +67,7 @@ fn spawn_explosion(
                         .first
                 },
             },
-            transform: player_transform.clone(),
+            transform: *player_transform,
             ..default()
         },
         unsafe {
@@ -98,3 +111,70 @@ fn animate_explosion(
         }
     }
 }
+
+/// Spawns a [`MiniExplosion`] at `translation`.
+pub fn spawn_mini_explosion(
+    commands: &mut Commands,
+    scene_assets: &SceneAssets,
+    translation: Vec3,
+) {
+    let explosion = &scene_assets.explosion;
+    let (Some(layout), Some(indices), Some(timer)) = (
+        explosion.texture_layout.clone(),
+        explosion.animation_indices.clone(),
+        explosion.animation_timer.clone(),
+    ) else {
+        return;
+    };
+    commands.spawn((
+        SpriteSheetBundle {
+            texture: explosion.image.clone(),
+            atlas: TextureAtlas {
+                layout,
+                index: indices.first,
+            },
+            transform: Transform {
+                translation,
+                scale: Vec3::splat(MINI_EXPLOSION_SCALE),
+                ..default()
+            },
+            ..default()
+        },
+        indices,
+        timer,
+        MiniExplosion,
+    ));
+}
+
+fn animate_mini_explosions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (
+            Entity,
+            &AnimationIndices,
+            &mut AnimationTimer,
+            &mut TextureAtlas,
+        ),
+        With<MiniExplosion>,
+    >,
+) {
+    for (entity, indices, mut timer, mut atlas) in query.iter_mut() {
+        timer.tick(time.delta());
+        if !timer.just_finished() {
+            continue;
+        }
+        // `last` is one past the final frame
+        if atlas.index + 1 < indices.last {
+            atlas.index += 1;
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn despawn_mini_explosions(mut commands: Commands, query: Query<Entity, With<MiniExplosion>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -3,20 +3,37 @@ use bevy_rapier2d::prelude::*;
 use rand::distributions::{Distribution, Uniform};
 
 use crate::{
-    /*camera::Background, */ graphics::SceneAssets, player::Player, schedule::InGameSet,
-    screen_bound_collision_detection::handle_screen_bound_collisions, state::GameState,
+    archetype::{choose_archetype, FireballArchetype},
+    collision::{fireball_groups, FireballCollisionMode, Hazard},
+    config::GameConfig,
+    difficulty::DifficultyProgress,
+    graphics::SceneAssets,
+    lifetime::Lifetime,
+    player::Player,
+    rng::GameRng,
+    schedule::InGameSet,
+    state::{GameState, OnRunEnd, OnRunStart},
+    tuning::GameTuning,
     ui::GameData,
 };
 
-pub const FIREBALL_SCALE: f32 = 0.049;
-const FIREBALL_SPAWN_TIME: f64 = 10.0;
-pub const INITIAL_FIREBALL_SPEED: f32 = 40.0;
-const SPEED_MULTIPLIER: f32 = 1.75;
-const FIREBALL_RADIUS: f32 = 512.0;
+// Keeps fireballs from appearing half outside of the window
+const SPAWN_MARGIN: f32 = 100.0;
+const SPAWN_ATTEMPTS: usize = 16;
+const WARNING_COLOR: Color = Color::rgba(1.0, 0.2, 0.2, 0.6);
+// Blinks per second
+const WARNING_BLINK_RATE: f32 = 8.0;
 
 #[derive(Component)]
 pub struct Fireball;
 
+/// Marks the spot where a fireball is about to appear.
+#[derive(Component)]
+pub struct SpawnWarning {
+    timer: Timer,
+    archetype: FireballArchetype,
+}
+
 pub struct FireballPlugin;
 
 #[derive(Resource, Default)]
@@ -27,73 +44,175 @@ pub struct FireballSpeed {
 impl Plugin for FireballPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FireballSpeed>()
-            .insert_resource(Time::<Fixed>::from_seconds(FIREBALL_SPAWN_TIME))
             .add_systems(
                 FixedUpdate,
                 spawn_fireball.run_if(in_state(GameState::InGame)),
             )
             .add_systems(
                 Update,
-                (/*rotate_fireball, */handle_screen_bound_collisions::<Fireball>)
-                    .in_set(InGameSet::EntityUpdates),
+                materialize_fireballs.in_set(InGameSet::EntityUpdates),
             )
-            .add_systems(FixedPostUpdate, increase_speed)
-            .add_systems(OnExit(GameState::GameOver), despawn_fireballs);
+            .add_systems(OnRunStart, reset_spawn_timer)
+            .add_systems(OnRunEnd, (despawn_fireballs, despawn_spawn_warnings));
     }
 }
 
+/// Places a [`SpawnWarning`] where the next fireball will appear, away from the player.
 pub fn spawn_fireball(
     mut commands: Commands,
     window_query: Query<&Window, With<PrimaryWindow>>,
     player_position_query: Query<&Transform, With<Player>>,
     scene_assets: Res<SceneAssets>,
+    mut rng: ResMut<GameRng>,
+    tuning: Res<GameTuning>,
+    difficulty_progress: Res<DifficultyProgress>,
+) {
+    // Fireball speed and spawn rate follow the difficulty curve, see `difficulty.rs`
+    let Ok(player_pos) = player_position_query.get_single() else {
+        return;
+    };
+    let window = window_query.get_single().unwrap();
+    let spawn_point = pick_spawn_point(
+        &mut rng,
+        Vec2::new(window.width(), window.height()) / 2.0 - SPAWN_MARGIN,
+        player_pos.translation.xy(),
+        tuning.min_spawn_distance,
+    );
+    let archetype = choose_archetype(&tuning.archetypes, &mut rng, difficulty_progress.step);
+
+    commands.spawn((
+        SpriteBundle {
+            texture: scene_assets.fireball.image.clone(),
+            sprite: Sprite {
+                color: WARNING_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                translation: spawn_point.extend(1.0),
+                scale: Vec2::splat(tuning.fireball_scale * tuning.archetype(archetype).size_scale)
+                    .extend(0.0),
+                ..default()
+            },
+            ..default()
+        },
+        SpawnWarning {
+            timer: Timer::from_seconds(tuning.spawn_warning_time, TimerMode::Once),
+            archetype,
+        },
+    ));
+}
+
+/// Picks a random point inside `half_extents` at least `min_distance` away from
+/// `player`. When none is found, falls back to the edge point farthest from the player.
+fn pick_spawn_point(
+    rng: &mut GameRng,
+    half_extents: Vec2,
+    player: Vec2,
+    min_distance: f32,
+) -> Vec2 {
+    let between_width = Uniform::from(-half_extents.x..half_extents.x);
+    let between_height = Uniform::from(-half_extents.y..half_extents.y);
+    for _ in 0..SPAWN_ATTEMPTS {
+        let point = Vec2::new(between_width.sample(rng), between_height.sample(rng));
+        if point.distance(player) >= min_distance {
+            return point;
+        }
+    }
+    let x = between_width.sample(rng);
+    let y = between_height.sample(rng);
+    [
+        Vec2::new(x, half_extents.y),
+        Vec2::new(x, -half_extents.y),
+        Vec2::new(half_extents.x, y),
+        Vec2::new(-half_extents.x, y),
+    ]
+    .into_iter()
+    .max_by(|a, b| a.distance(player).total_cmp(&b.distance(player)))
+    .unwrap()
+}
+
+/// Blinks the spawn warnings and turns the expired ones into fireballs.
+#[allow(clippy::too_many_arguments)]
+pub fn materialize_fireballs(
+    mut commands: Commands,
+    mut warning_query: Query<(Entity, &mut SpawnWarning, &mut Sprite, &Transform)>,
+    player_position_query: Query<&Transform, With<Player>>,
+    scene_assets: Res<SceneAssets>,
     mut fireball_speed: ResMut<FireballSpeed>,
     mut game_data: ResMut<GameData>,
-    // mut background_query: Query<Entity, With<Background>>,
+    tuning: Res<GameTuning>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
 ) {
     // Remember to fire an event whenever we spawn a fireball in order to update the counter
-    // Fireball speed should be incremented or multiplied every 10 balls spawned, think of creating anouther system or add additional checks here
     let Ok(player_pos) = player_position_query.get_single() else {
         return;
     };
-    let window = window_query.get_single().unwrap();
-    let height = window.height() / 2.0;
-    let width = window.width() / 2.0;
-    let between_width = Uniform::from(-width + 100.0..width - 100.0);
-    let between_height = Uniform::from(-height + 100.0..height - 100.0);
-    let mut rng = rand::thread_rng();
-
-    let fireball_translation = Vec3::new(
-        between_width.sample(&mut rng),
-        between_height.sample(&mut rng),
-        1.0,
-    );
+    for (warning_entity, mut warning, mut sprite, warning_transform) in warning_query.iter_mut() {
+        warning.timer.tick(time.delta());
+        if !warning.timer.finished() {
+            let blink_on =
+                ((warning.timer.elapsed_secs() * WARNING_BLINK_RATE) as u32).is_multiple_of(2);
+            let alpha = if blink_on { WARNING_COLOR.a() } else { 0.1 };
+            sprite.color.set_a(alpha);
+            continue;
+        }
+        commands.entity(warning_entity).despawn_recursive();
+
+        let fireball_translation = warning_transform.translation;
 
-    (*fireball_speed).speed = game_data.current_fireballs_speed;
-
-    // let background = background_query.get_single_mut().unwrap();
-
-    let vel = (fireball_translation.xy() - player_pos.translation.xy()).normalize()
-        * (*fireball_speed).speed;
-    /*let fireball_id = */
-    commands
-        .spawn((
-            SpriteBundle {
-                texture: scene_assets.fireball.image.clone(),
-                transform: Transform {
-                    translation: fireball_translation,
-                    // rotation: Quat::from_rotation_z(vel.angle_between(Vec2::new(-1.0, -1.0))),
-                    // rotation: Quat::from_axis_angle(Vec3::Z, 1.57),
-                    scale: Vec2::new(FIREBALL_SCALE, 0.0).xxy(),
-                    ..default()
-                },
+        fireball_speed.speed = game_data.current_fireballs_speed;
+
+        let speed = fireball_speed.speed * tuning.archetype(warning.archetype).speed_scale;
+        let vel =
+            (fireball_translation.xy() - player_pos.translation.xy()).normalize_or_zero() * speed;
+        spawn_fireball_entity(
+            &mut commands,
+            &scene_assets,
+            &tuning,
+            config.fireball_collisions,
+            warning.archetype,
+            fireball_translation,
+            vel,
+        );
+
+        game_data.n_balls += 1;
+    }
+}
+
+/// Spawns a fireball of the given archetype and returns its entity.
+pub fn spawn_fireball_entity(
+    commands: &mut Commands,
+    scene_assets: &SceneAssets,
+    tuning: &GameTuning,
+    collision_mode: FireballCollisionMode,
+    archetype: FireballArchetype,
+    translation: Vec3,
+    linvel: Vec2,
+) -> Entity {
+    let archetype_tuning = tuning.archetype(archetype);
+    let mut fireball = commands.spawn((
+        SpriteBundle {
+            texture: scene_assets.fireball.image.clone(),
+            sprite: Sprite {
+                color: archetype.color(),
                 ..default()
             },
-            Fireball,
-            RigidBody::Dynamic,
-        ))
+            transform: Transform {
+                translation,
+                scale: Vec2::splat(tuning.fireball_scale * archetype_tuning.size_scale).extend(0.0),
+                ..default()
+            },
+            ..default()
+        },
+        Fireball,
+        Hazard,
+        archetype,
+        RigidBody::Dynamic,
+    ));
+    fireball
         .insert(Velocity {
-            linvel: vel,
+            linvel,
             angvel: 0.0,
         })
         .insert(Damping {
@@ -103,21 +222,29 @@ pub fn spawn_fireball(
         .insert(Friction::coefficient(0.0))
         .insert(Restitution::coefficient(1.0))
         .insert(GravityScale(0.0))
-        .insert(Collider::ball(FIREBALL_RADIUS))
+        .insert(Collider::ball(tuning.fireball_radius))
+        .insert(fireball_groups(collision_mode))
         .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC)
         .insert(ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS);
-    // .id();
-
-    // commands.entity(background).push_children(&[fireball_id]);
+    archetype.insert_behavior(&mut fireball, tuning);
+    if let Some(lifetime) = Lifetime::from_tuning(tuning) {
+        fireball.insert(lifetime);
+    }
+    fireball.id()
+}
 
-    game_data.n_balls += 1;
+// The fixed clock keeps ticking outside of InGame, so every run has to start
+// from an empty accumulator to spawn its first fireball at the same time.
+fn reset_spawn_timer(mut fixed_time: ResMut<Time<Fixed>>) {
+    let overstep = fixed_time.overstep();
+    fixed_time.discard_overstep(overstep);
 }
 
-// pub fn rotate_fireball(mut query: Query<(&Velocity, &mut Transform), With<Fireball>>) {
-//     for (vel, mut transform) in &mut query {
-//         transform.rotation = Quat::from_rotation_z(vel.linvel.angle_between(Vec2::new(0.0, 1.0)));
-//     }
-// }
+fn despawn_spawn_warnings(mut commands: Commands, query: Query<Entity, With<SpawnWarning>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
 pub fn despawn_fireballs(mut commands: Commands, fireball_query: Query<Entity, With<Fireball>>) {
     for entity in fireball_query.iter() {
@@ -125,14 +252,38 @@ pub fn despawn_fireballs(mut commands: Commands, fireball_query: Query<Entity, W
     }
 }
 
-pub fn increase_speed(
-    mut query: Query<&mut Velocity, With<Fireball>>,
-    mut game_data: ResMut<GameData>,
-) {
-    if game_data.n_balls % 10 == 0 {
-        game_data.current_fireballs_speed *= SPEED_MULTIPLIER;
-        query
-            .par_iter_mut()
-            .for_each(|mut ball_velocity| ball_velocity.linvel *= SPEED_MULTIPLIER);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_EXTENTS: Vec2 = Vec2::new(300.0, 200.0);
+
+    #[test]
+    fn spawn_points_keep_their_distance() {
+        let mut rng = GameRng::new(1);
+        for player in [Vec2::ZERO, Vec2::new(250.0, 150.0), Vec2::new(-100.0, 0.0)] {
+            for _ in 0..200 {
+                let point = pick_spawn_point(&mut rng, HALF_EXTENTS, player, 150.0);
+                assert!(point.abs().cmple(HALF_EXTENTS).all(), "{point}");
+                assert!(
+                    point.distance(player) >= 150.0,
+                    "{point} too close to {player}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_points_fall_back_to_the_farthest_edge() {
+        let mut rng = GameRng::new(2);
+        // Nothing is far enough, so the edges away from the player are used
+        let player = Vec2::new(250.0, 150.0);
+        for _ in 0..50 {
+            let point = pick_spawn_point(&mut rng, HALF_EXTENTS, player, 10_000.0);
+            assert!(
+                point.x == -HALF_EXTENTS.x || point.y == -HALF_EXTENTS.y,
+                "{point}"
+            );
+        }
     }
 }
@@ -0,0 +1,50 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// State of every connected gamepad. Gamepads can come and go at any time,
+/// [`Gamepads`] only lists the ones currently plugged in.
+#[derive(SystemParam)]
+pub struct GamepadInput<'w> {
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    buttons: Res<'w, ButtonInput<GamepadButton>>,
+}
+
+impl GamepadInput<'_> {
+    /// Position of the left stick of the first gamepad in use. Positions
+    /// within `dead_zone` of the center are ignored.
+    pub fn left_stick(&self, dead_zone: f32) -> Option<Vec2> {
+        self.gamepads.iter().find_map(|gamepad| {
+            let stick = Vec2::new(
+                self.axes
+                    .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                    .unwrap_or_default(),
+                self.axes
+                    .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                    .unwrap_or_default(),
+            );
+            (stick.length() > dead_zone).then_some(stick)
+        })
+    }
+
+    pub fn pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.buttons
+                .pressed(GamepadButton::new(gamepad, button_type))
+        })
+    }
+
+    pub fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.buttons
+                .just_pressed(GamepadButton::new(gamepad, button_type))
+        })
+    }
+
+    /// Any button pressed this frame on a connected gamepad.
+    pub fn any_just_pressed(&self) -> Option<GamepadButtonType> {
+        self.buttons
+            .get_just_pressed()
+            .find(|button| self.gamepads.contains(button.gamepad))
+            .map(|button| button.button_type)
+    }
+}
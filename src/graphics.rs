@@ -39,6 +39,9 @@ pub struct SceneAssets {
 
 pub struct AssetLoaderPlugin;
 
+/// Used instead of [`AssetLoaderPlugin`] when the game runs without a renderer.
+pub struct PlaceholderAssetsPlugin;
+
 impl Plugin for AssetLoaderPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SceneAssets>()
@@ -46,6 +49,14 @@ impl Plugin for AssetLoaderPlugin {
     }
 }
 
+impl Plugin for PlaceholderAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SceneAssets>()
+            .init_asset::<TextureAtlasLayout>()
+            .add_systems(PreStartup, load_placeholder_assets);
+    }
+}
+
 impl EntityAssets<StaticEntity> {
     pub fn new(image: Handle<Image>) -> Self {
         Self {
@@ -104,44 +115,71 @@ pub fn load_assets(
     mut scene_assets: ResMut<SceneAssets>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
-    let background = EntityAssets::<StaticEntity>::new(asset_server.load("background_1.png"));
-    let explosion = EntityAssets::<AnimatedEntity>::new(
-        asset_server.load("explosion_sequece.png"),
+    *scene_assets = SceneAssets {
+        background: EntityAssets::<StaticEntity>::new(asset_server.load("background_1.png")),
+        player: player_assets(asset_server.load("player.png"), &mut texture_atlas_layouts),
+        fireball: EntityAssets::<StaticEntity>::new(asset_server.load("fireball.png")),
+        explosion: explosion_assets(
+            asset_server.load("explosion_sequece.png"),
+            &mut texture_atlas_layouts,
+        ),
+        explosion_audio: asset_server.load("explosion.ogg"),
+    };
+}
+
+/// Fills [`SceneAssets`] with default image handles but real atlas layouts, so
+/// gameplay systems can spawn their entities without any file being loaded.
+pub fn load_placeholder_assets(
+    mut scene_assets: ResMut<SceneAssets>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    *scene_assets = SceneAssets {
+        background: EntityAssets::<StaticEntity>::new(Handle::default()),
+        player: player_assets(Handle::default(), &mut texture_atlas_layouts),
+        fireball: EntityAssets::<StaticEntity>::new(Handle::default()),
+        explosion: explosion_assets(Handle::default(), &mut texture_atlas_layouts),
+        explosion_audio: Handle::default(),
+    };
+}
+
+fn player_assets(
+    image: Handle<Image>,
+    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+) -> EntityAssets<AnimatedEntity> {
+    EntityAssets::<AnimatedEntity>::new(
+        image,
         texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
-            Vec2::new(240.0, 240.0),
-            8,
-            6,
+            Vec2::new(PLAYER_PIXELS, PLAYER_PIXELS),
+            4,
+            4,
             None,
             None,
         )),
         AnimationIndices {
             first: 0,
-            last: 6 * 8,
+            last: 4 * 4,
         },
-        AnimationTimer(Timer::from_seconds(0.04, TimerMode::Repeating)),
-    );
-    let fireball = EntityAssets::<StaticEntity>::new(asset_server.load("fireball.png"));
-    let player = EntityAssets::<AnimatedEntity>::new(
-        asset_server.load("player.png"),
+        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+    )
+}
+
+fn explosion_assets(
+    image: Handle<Image>,
+    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+) -> EntityAssets<AnimatedEntity> {
+    EntityAssets::<AnimatedEntity>::new(
+        image,
         texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
-            Vec2::new(PLAYER_PIXELS, PLAYER_PIXELS),
-            4,
-            4,
+            Vec2::new(240.0, 240.0),
+            8,
+            6,
             None,
             None,
         )),
         AnimationIndices {
             first: 0,
-            last: 4 * 4,
+            last: 6 * 8,
         },
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-    );
-    let explosion_audio = asset_server.load("explosion.ogg");
-    *scene_assets = SceneAssets {
-        background,
-        player,
-        fireball,
-        explosion,
-        explosion_audio,
-    };
+        AnimationTimer(Timer::from_seconds(0.04, TimerMode::Repeating)),
+    )
 }
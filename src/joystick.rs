@@ -0,0 +1,241 @@
+use bevy::{input::touch::TouchPhase, prelude::*};
+#[cfg(not(target_os = "android"))]
+use bevy::{
+    input::{mouse::MouseButtonInput, ButtonState, InputSystem},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    actions::{update_action_state, Action, ActionState},
+    state::GameState,
+};
+
+/// Distance in logical pixels between the touch-down point and the finger at
+/// which the joystick is fully tilted.
+pub const JOYSTICK_RADIUS: f32 = 80.0;
+// Tilts below this fraction of the radius don't steer
+const JOYSTICK_DEAD_ZONE: f32 = 0.15;
+// Two touches closer than this in time and in pixels make a double tap, which dashes
+const DOUBLE_TAP_TIME: f32 = 0.3;
+const DOUBLE_TAP_DISTANCE: f32 = 48.0;
+const KNOB_SIZE: f32 = 56.0;
+const BASE_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+const KNOB_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.5);
+/// Id of the touch emulated with the left mouse button, away from the small
+/// ids of real touches.
+pub const MOUSE_TOUCH_ID: u64 = u64::MAX;
+
+/// Joystick following the first finger on the screen: it appears where the
+/// finger touched down and tilts toward where the finger is now.
+#[derive(Resource, Default)]
+pub struct VirtualJoystick {
+    touch: Option<u64>,
+    origin: Vec2,
+    position: Vec2,
+    /// When and where the joystick last appeared, in seconds of game time.
+    last_tap: Option<(f32, Vec2)>,
+}
+
+#[derive(Component)]
+struct JoystickBase;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+pub struct VirtualJoystickPlugin;
+
+impl Plugin for VirtualJoystickPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VirtualJoystick>()
+            .add_systems(Startup, spawn_joystick)
+            .add_systems(
+                PreUpdate,
+                (track_joystick_touch, steer_with_joystick)
+                    .chain()
+                    .after(update_action_state)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(OnExit(GameState::InGame), release_joystick)
+            .add_systems(
+                Update,
+                update_joystick.run_if(resource_changed::<VirtualJoystick>),
+            );
+        #[cfg(not(target_os = "android"))]
+        app.add_systems(
+            PreUpdate,
+            emulate_touch_with_mouse
+                .after(InputSystem)
+                .before(track_joystick_touch),
+        );
+    }
+}
+
+impl VirtualJoystick {
+    /// Tilt of the joystick while a finger is on it: the direction of the
+    /// finger from the touch-down point, up being positive, with a length
+    /// growing from 0 to 1 at [`JOYSTICK_RADIUS`].
+    pub fn value(&self) -> Option<Vec2> {
+        self.touch?;
+        let offset = (self.position - self.origin) / JOYSTICK_RADIUS;
+        // Screen coordinates grow downward
+        Some(Vec2::new(offset.x, -offset.y).clamp_length_max(1.0))
+    }
+
+    /// Where the finger touched down, in logical window coordinates.
+    pub fn origin(&self) -> Option<Vec2> {
+        self.touch.map(|_| self.origin)
+    }
+}
+
+fn track_joystick_touch(
+    mut touch_events: EventReader<TouchInput>,
+    mut joystick: ResMut<VirtualJoystick>,
+    mut actions: ResMut<ActionState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for event in touch_events.read() {
+        match event.phase {
+            // Other fingers are ignored while one holds the joystick
+            TouchPhase::Started if joystick.touch.is_none() => {
+                joystick.touch = Some(event.id);
+                joystick.origin = event.position;
+                joystick.position = event.position;
+                let double_tap = joystick.last_tap.is_some_and(|(time, position)| {
+                    now - time <= DOUBLE_TAP_TIME
+                        && position.distance(event.position) <= DOUBLE_TAP_DISTANCE
+                });
+                if double_tap {
+                    actions.press(Action::Dash);
+                    joystick.last_tap = None;
+                } else {
+                    joystick.last_tap = Some((now, event.position));
+                }
+            }
+            TouchPhase::Moved if joystick.touch == Some(event.id) => {
+                joystick.position = event.position;
+            }
+            TouchPhase::Ended | TouchPhase::Canceled if joystick.touch == Some(event.id) => {
+                joystick.touch = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Only the direction steers, the player always moves at full speed
+fn steer_with_joystick(joystick: Res<VirtualJoystick>, mut actions: ResMut<ActionState>) {
+    if let Some(value) = joystick
+        .value()
+        .filter(|value| value.length() > JOYSTICK_DEAD_ZONE)
+    {
+        actions.axis = Some(value);
+        actions.joystick = true;
+    }
+}
+
+fn release_joystick(mut joystick: ResMut<VirtualJoystick>) {
+    joystick.touch = None;
+    joystick.last_tap = None;
+}
+
+/// Turns the left mouse button and the cursor into a touch, so the joystick
+/// and everything else reading touches also work with a mouse.
+#[cfg(not(target_os = "android"))]
+fn emulate_touch_with_mouse(
+    mut mouse_button_events: EventReader<MouseButtonInput>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut touch_events: EventWriter<TouchInput>,
+    window_query: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut cursor_position: Local<Option<Vec2>>,
+    mut pressed: Local<bool>,
+) {
+    let Ok((window, window_state)) = window_query.get_single() else {
+        return;
+    };
+    let touch = |phase, position| TouchInput {
+        phase,
+        position,
+        window,
+        force: None,
+        id: MOUSE_TOUCH_ID,
+    };
+    for event in cursor_events.read() {
+        *cursor_position = Some(event.position);
+        if *pressed {
+            touch_events.send(touch(TouchPhase::Moved, event.position));
+        }
+    }
+    let Some(position) = cursor_position.or(window_state.cursor_position()) else {
+        return;
+    };
+    for event in mouse_button_events.read() {
+        match (event.button, event.state) {
+            (MouseButton::Left, ButtonState::Pressed) if !*pressed => {
+                *pressed = true;
+                touch_events.send(touch(TouchPhase::Started, position));
+            }
+            (MouseButton::Left, ButtonState::Released) if *pressed => {
+                *pressed = false;
+                touch_events.send(touch(TouchPhase::Ended, position));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_joystick(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(2.0 * JOYSTICK_RADIUS),
+                    height: Val::Px(2.0 * JOYSTICK_RADIUS),
+                    ..default()
+                },
+                background_color: BASE_COLOR.into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            JoystickBase,
+        ))
+        .with_children(|base| {
+            base.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(KNOB_SIZE),
+                        height: Val::Px(KNOB_SIZE),
+                        ..default()
+                    },
+                    background_color: KNOB_COLOR.into(),
+                    ..default()
+                },
+                JoystickKnob,
+            ));
+        });
+}
+
+fn update_joystick(
+    joystick: Res<VirtualJoystick>,
+    mut base_query: Query<(&mut Style, &mut Visibility), With<JoystickBase>>,
+    mut knob_query: Query<&mut Style, (With<JoystickKnob>, Without<JoystickBase>)>,
+) {
+    let Ok((mut base_style, mut visibility)) = base_query.get_single_mut() else {
+        return;
+    };
+    let (Some(origin), Some(value)) = (joystick.origin(), joystick.value()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+    base_style.left = Val::Px(origin.x - JOYSTICK_RADIUS);
+    base_style.top = Val::Px(origin.y - JOYSTICK_RADIUS);
+    if let Ok(mut knob_style) = knob_query.get_single_mut() {
+        let knob_offset = Vec2::new(value.x, -value.y) * JOYSTICK_RADIUS;
+        knob_style.left = Val::Px(JOYSTICK_RADIUS - KNOB_SIZE / 2.0 + knob_offset.x);
+        knob_style.top = Val::Px(JOYSTICK_RADIUS - KNOB_SIZE / 2.0 + knob_offset.y);
+    }
+}
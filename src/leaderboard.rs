@@ -0,0 +1,266 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{update_action_state, ActionState},
+    difficulty::{Difficulty, DifficultyPreset},
+    gamepad::GamepadInput,
+    menu::MenuRoot,
+    rng::GameRng,
+    score::Score,
+    state::{GameState, StateFlags},
+    stats::RunClock,
+    storage::ScoreStorage,
+    ui::store_new_records,
+};
+
+pub const LEADERBOARD_KEY: &str = "leaderboard";
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 12;
+/// Name of the entries saved without typing any.
+pub const DEFAULT_NAME: &str = "Player";
+
+/// A run on the leaderboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: u64,
+    pub time_survived: Duration,
+    /// Seconds since the Unix epoch when the run ended.
+    pub date: u64,
+    pub difficulty: DifficultyPreset,
+    pub seed: u64,
+}
+
+/// Best runs played on this device, highest score first.
+#[derive(Resource, Default, Clone, Debug, PartialEq)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+/// The leaderboard as it is persisted. Every change of the format adds a
+/// variant, and the older ones are migrated when loaded.
+#[derive(Serialize, Deserialize)]
+pub enum StoredLeaderboard {
+    V1(Vec<LeaderboardEntry>),
+}
+
+/// Name being typed for a run that made it to the leaderboard. It is saved
+/// once `submitted` is set, and dropped without an entry once `skipped` is.
+#[derive(Resource, Default)]
+pub struct NameEntry {
+    pub name: String,
+    pub submitted: bool,
+    pub skipped: bool,
+}
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Leaderboard>()
+            .add_systems(Startup, load_leaderboard)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                start_name_entry.after(store_new_records),
+            )
+            .add_systems(OnExit(GameState::GameOver), cancel_name_entry)
+            // Before the menus and the state changes read the actions
+            .add_systems(
+                PreUpdate,
+                type_name
+                    .after(update_action_state)
+                    .run_if(resource_exists::<NameEntry>),
+            )
+            .add_systems(Update, save_name_entry.run_if(resource_exists::<NameEntry>));
+    }
+}
+
+impl Leaderboard {
+    /// Whether a run with this score would get an entry.
+    pub fn qualifies(&self, score: u64) -> bool {
+        score > 0
+            && (self.entries.len() < LEADERBOARD_SIZE
+                || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds `entry` after the ones with the same score or more, and returns
+    /// its index if it made it into the top entries.
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let index = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+        if index >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.entries.insert(index, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(index)
+    }
+
+    pub fn load(storage: &ScoreStorage) -> Self {
+        match storage.get::<StoredLeaderboard>(LEADERBOARD_KEY) {
+            Ok(Some(stored)) => stored.into(),
+            Ok(None) => Self::default(),
+            Err(error) => {
+                warn!("ignoring stored leaderboard: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn store(&self, storage: &mut ScoreStorage) {
+        let stored = StoredLeaderboard::V1(self.entries.clone());
+        if let Err(error) = storage.set(LEADERBOARD_KEY, &stored) {
+            error!("failed to store leaderboard: {error}");
+        }
+    }
+}
+
+impl From<StoredLeaderboard> for Leaderboard {
+    fn from(stored: StoredLeaderboard) -> Self {
+        let StoredLeaderboard::V1(mut entries) = stored;
+        // Hand-edited files may be out of order
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        entries.truncate(LEADERBOARD_SIZE);
+        Self { entries }
+    }
+}
+
+impl NameEntry {
+    /// The typed name, or [`DEFAULT_NAME`] if none was typed.
+    pub fn name_or_default(&self) -> &str {
+        match self.name.trim() {
+            "" => DEFAULT_NAME,
+            name => name,
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as a `yyyy-mm-dd` UTC date.
+pub fn format_date(seconds: u64) -> String {
+    // Howard Hinnant's days-to-civil algorithm
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn load_leaderboard(storage: Res<ScoreStorage>, mut leaderboard: ResMut<Leaderboard>) {
+    *leaderboard = Leaderboard::load(&storage);
+}
+
+fn start_name_entry(mut commands: Commands, leaderboard: Res<Leaderboard>, score: Res<Score>) {
+    if leaderboard.qualifies(score.points) {
+        commands.init_resource::<NameEntry>();
+    }
+}
+
+fn cancel_name_entry(mut commands: Commands) {
+    commands.remove_resource::<NameEntry>();
+}
+
+fn type_name(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    gamepad_input: GamepadInput,
+    mut name_entry: ResMut<NameEntry>,
+    mut actions: ResMut<ActionState>,
+    state_flags: Res<StateFlags>,
+) {
+    // The prompt shows up once the explosion is over
+    if !state_flags.explosion_ended {
+        keyboard_events.clear();
+        return;
+    }
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => {
+                for character in text.chars() {
+                    if !character.is_control() && name_entry.name.chars().count() < MAX_NAME_LENGTH
+                    {
+                        name_entry.name.push(character);
+                    }
+                }
+            }
+            Key::Space if name_entry.name.chars().count() < MAX_NAME_LENGTH => {
+                name_entry.name.push(' ');
+            }
+            Key::Backspace => {
+                name_entry.name.pop();
+            }
+            Key::Enter => name_entry.submitted = true,
+            Key::Escape => name_entry.skipped = true,
+            _ => {}
+        }
+    }
+    if gamepad_input.just_pressed(GamepadButtonType::South)
+        || gamepad_input.just_pressed(GamepadButtonType::Start)
+    {
+        name_entry.submitted = true;
+    } else if gamepad_input.just_pressed(GamepadButtonType::East) {
+        name_entry.skipped = true;
+    }
+    // The keys typed must not also retry the run or move through the menu
+    actions.clear();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn save_name_entry(
+    mut commands: Commands,
+    name_entry: Res<NameEntry>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut storage: ResMut<ScoreStorage>,
+    score: Res<Score>,
+    clock: Res<RunClock>,
+    difficulty: Res<Difficulty>,
+    rng: Res<GameRng>,
+    menu_query: Query<Entity, With<MenuRoot>>,
+) {
+    if !name_entry.submitted && !name_entry.skipped {
+        return;
+    }
+    if name_entry.submitted {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        leaderboard.insert(LeaderboardEntry {
+            name: name_entry.name_or_default().to_string(),
+            score: score.points,
+            time_survived: clock.elapsed(),
+            date,
+            difficulty: difficulty.preset,
+            seed: rng.seed(),
+        });
+        leaderboard.store(&mut storage);
+    }
+    commands.remove_resource::<NameEntry>();
+    // Makes room for the GameOver panel
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
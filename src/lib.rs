@@ -1,27 +1,64 @@
+pub mod actions;
+mod archetype;
 mod camera;
-mod explosion;
+pub mod collision;
+pub mod config;
+pub mod dash;
+pub mod difficulty;
+pub mod explosion;
 mod fireball;
+pub mod gamepad;
 mod graphics;
-mod player;
-mod scene;
+pub mod joystick;
+pub mod leaderboard;
+mod lifetime;
+pub mod menu;
+pub mod pickup;
+pub mod player;
+pub mod record;
+pub mod replay;
+pub mod rng;
 mod schedule;
+pub mod score;
 mod screen_bound_collision_detection;
-mod state;
-mod ui;
+pub mod state;
+pub mod stats;
+pub mod storage;
+pub mod tuning;
+pub mod ui;
 
+use actions::ActionsPlugin;
+use archetype::ArchetypePlugin;
 use bevy::{
+    asset::AssetPlugin,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    hierarchy::HierarchyPlugin,
+    input::InputPlugin,
     prelude::*,
-    window::{PresentMode, WindowTheme},
+    time::TimeUpdateStrategy,
+    window::{ExitCondition, PresentMode, WindowTheme},
 };
-use camera::{CameraPlugin, BACKGROUND_SCALE};
+use camera::CameraPlugin;
+pub use config::GameConfig;
+use dash::DashPlugin;
+use difficulty::DifficultyPlugin;
 use explosion::ExplosionPlugin;
 use fireball::FireballPlugin;
-use graphics::AssetLoaderPlugin;
+use graphics::{AssetLoaderPlugin, PlaceholderAssetsPlugin};
+use joystick::VirtualJoystickPlugin;
+use leaderboard::LeaderboardPlugin;
+use lifetime::LifetimePlugin;
+use menu::MenuPlugin;
+use pickup::PickupPlugin;
 use player::PlayerPlugin;
+use replay::{ReplayMode, ReplayPlugin, DEFAULT_TIMESTEP};
+use rng::RngPlugin;
 use schedule::SchedulePlugin;
+use score::ScorePlugin;
 use screen_bound_collision_detection::ScreenCollisionDetectionPlugin;
 use state::StatePlugin;
+use stats::StatsPlugin;
+use tuning::TuningPlugin;
 use ui::UiPlugin;
 
 #[bevy_main]
@@ -30,45 +67,117 @@ fn main() {
 }
 
 pub fn run_game() {
-    App::new()
-        .insert_resource(ClearColor(Color::rgb(0.1, 0.0, 0.15)))
-        .insert_resource(AmbientLight {
-            color: Color::default(),
-            brightness: 0.75,
-        })
-        .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
+    let config = GameConfig::from_args().unwrap_or_else(|error| {
+        eprintln!("dodge_fire_ball: {error}");
+        std::process::exit(2);
+    });
+    build_game_app(config).run();
+}
+
+/// Assembles the whole game without running it, so it can also be driven
+/// frame by frame with [`step_frames`].
+pub fn build_game_app(mut config: GameConfig) -> App {
+    // Replays are only deterministic with a fixed timestep
+    match &config.replay {
+        ReplayMode::Off => {}
+        ReplayMode::Record(_) => {
+            config.fixed_delta.get_or_insert(DEFAULT_TIMESTEP);
+        }
+        ReplayMode::Play(replay) => {
+            config.seed = Some(replay.seed);
+            config.fixed_delta = Some(replay.timestep);
+        }
+    }
+    let mut app = App::new();
+    if config.headless {
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+            AssetPlugin::default(),
+            WindowPlugin {
                 primary_window: Some(Window {
                     title: "DodgeFireBall".into(),
-                    name: Some("dodgefireball.app".into()),
-                    resolution: (448. * BACKGROUND_SCALE, 298. * BACKGROUND_SCALE).into(),
-                    present_mode: PresentMode::AutoVsync,
-                    // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
-                    prevent_default_event_handling: false,
-                    window_theme: Some(WindowTheme::Dark),
-                    enabled_buttons: bevy::window::EnabledButtons {
-                        maximize: false,
-                        ..Default::default()
-                    },
-                    // This will spawn an invisible window
-                    // The window will be made visible in the make_visible() system after 3 frames.
-                    // This is useful when you want to avoid the white window that shows up before the GPU is ready to render the app.
-                    visible: true,
+                    resolution: config.window_resolution.into(),
                     ..default()
                 }),
-                ..default()
-            }),
-            LogDiagnosticsPlugin::default(),
-            FrameTimeDiagnosticsPlugin,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
         ))
-        .add_plugins(AssetLoaderPlugin)
+        .add_plugins(PlaceholderAssetsPlugin);
+    } else {
+        app.insert_resource(ClearColor(Color::rgb(0.1, 0.0, 0.15)))
+            .insert_resource(AmbientLight {
+                color: Color::default(),
+                brightness: 0.75,
+            })
+            .add_plugins((
+                DefaultPlugins.set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "DodgeFireBall".into(),
+                        name: Some("dodgefireball.app".into()),
+                        resolution: config.window_resolution.into(),
+                        present_mode: PresentMode::AutoVsync,
+                        // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
+                        prevent_default_event_handling: false,
+                        window_theme: Some(WindowTheme::Dark),
+                        enabled_buttons: bevy::window::EnabledButtons {
+                            maximize: false,
+                            ..Default::default()
+                        },
+                        // This will spawn an invisible window
+                        // The window will be made visible in the make_visible() system after 3 frames.
+                        // This is useful when you want to avoid the white window that shows up before the GPU is ready to render the app.
+                        visible: true,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    // Lets the tuning file hot-reload while developing
+                    watch_for_changes_override: Some(cfg!(all(feature = "dev", debug_assertions))),
+                    ..default()
+                }),
+                LogDiagnosticsPlugin::default(),
+                FrameTimeDiagnosticsPlugin,
+            ))
+            .add_plugins(AssetLoaderPlugin)
+            .add_plugins(TuningPlugin)
+            .add_plugins(CameraPlugin);
+    }
+    if let Some(delta) = config.fixed_delta {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(delta));
+    }
+    app.insert_resource(config.tuning.clone())
+        .insert_resource(config)
+        .add_plugins(RngPlugin)
+        .add_plugins(ActionsPlugin)
+        .add_plugins(VirtualJoystickPlugin)
         .add_plugins(PlayerPlugin)
+        .add_plugins(DashPlugin)
+        .add_plugins(ReplayPlugin)
         .add_plugins(FireballPlugin)
-        .add_plugins(CameraPlugin)
+        .add_plugins(ArchetypePlugin)
+        .add_plugins(LifetimePlugin)
+        .add_plugins(DifficultyPlugin)
+        .add_plugins(PickupPlugin)
         .add_plugins(UiPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(LeaderboardPlugin)
+        .add_plugins(StatsPlugin)
+        .add_plugins(ScorePlugin)
         .add_plugins(ScreenCollisionDetectionPlugin)
         .add_plugins(StatePlugin)
         .add_plugins(ExplosionPlugin)
-        .add_plugins(SchedulePlugin)
-        .run();
+        .add_plugins(SchedulePlugin);
+    app
+}
+
+/// Runs `frames` updates of an app returned by [`build_game_app`].
+pub fn step_frames(app: &mut App, frames: u32) {
+    for _ in 0..frames {
+        app.update();
+    }
 }
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    schedule::InGameSet, screen_bound_collision_detection::ScreenCollisionEvent, tuning::GameTuning,
+};
+
+/// Limits how long a fireball stays in the arena. It starts fading out as soon
+/// as one of the limits is reached.
+#[derive(Component)]
+pub struct Lifetime {
+    pub timer: Option<Timer>,
+    pub bounces_left: Option<u32>,
+}
+
+/// Fireball on its way out. It can't hurt anymore and is despawned once faded.
+#[derive(Component)]
+pub struct FadeOut {
+    pub timer: Timer,
+}
+
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (expire_lifetimes, fade_out)
+                .chain()
+                .in_set(InGameSet::DespwanEntities),
+        );
+    }
+}
+
+impl Lifetime {
+    /// Lifetime of a new fireball, if the tuning sets any limit.
+    pub fn from_tuning(tuning: &GameTuning) -> Option<Self> {
+        if tuning.fireball_lifetime.is_none() && tuning.fireball_max_bounces.is_none() {
+            return None;
+        }
+        Some(Self {
+            timer: tuning
+                .fireball_lifetime
+                .map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+            bounces_left: tuning.fireball_max_bounces,
+        })
+    }
+
+    fn expired(&self) -> bool {
+        self.timer.as_ref().is_some_and(Timer::finished) || self.bounces_left == Some(0)
+    }
+}
+
+fn expire_lifetimes(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<ScreenCollisionEvent>,
+    mut query: Query<(Entity, &mut Lifetime)>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
+) {
+    for &ScreenCollisionEvent { entity } in collision_event_reader.read() {
+        if let Ok((_, mut lifetime)) = query.get_mut(entity) {
+            if let Some(bounces_left) = lifetime.bounces_left.as_mut() {
+                *bounces_left = bounces_left.saturating_sub(1);
+            }
+        }
+    }
+    for (entity, mut lifetime) in query.iter_mut() {
+        if let Some(timer) = lifetime.timer.as_mut() {
+            timer.tick(time.delta());
+        }
+        if lifetime.expired() {
+            commands
+                .entity(entity)
+                .remove::<Lifetime>()
+                .insert(ColliderDisabled)
+                .insert(FadeOut {
+                    timer: Timer::from_seconds(tuning.fireball_fade_time, TimerMode::Once),
+                });
+        }
+    }
+}
+
+fn fade_out(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FadeOut, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut fade_out, mut sprite) in query.iter_mut() {
+        fade_out.timer.tick(time.delta());
+        if fade_out.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            sprite.color.set_a(fade_out.timer.fraction_remaining());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn lifetime_app(tuning: GameTuning) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_event::<ScreenCollisionEvent>()
+            .init_resource::<Time>()
+            .add_plugins(LifetimePlugin);
+        let lifetime = Lifetime::from_tuning(&tuning).unwrap();
+        app.insert_resource(tuning);
+        let fireball = app.world.spawn((lifetime, Sprite::default())).id();
+        (app, fireball)
+    }
+
+    fn step(app: &mut App, seconds: f32) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn no_limit_no_lifetime() {
+        assert!(Lifetime::from_tuning(&GameTuning::default()).is_none());
+    }
+
+    #[test]
+    fn fireballs_fade_out_when_their_time_is_up() {
+        let (mut app, fireball) = lifetime_app(GameTuning {
+            fireball_lifetime: Some(1.0),
+            fireball_fade_time: 0.5,
+            ..default()
+        });
+        for _ in 0..9 {
+            step(&mut app, 0.1);
+        }
+        assert!(app.world.get::<Lifetime>(fireball).is_some());
+        assert!(app.world.get::<FadeOut>(fireball).is_none());
+
+        // Fading starts in the frame the time is up
+        step(&mut app, 0.1);
+        assert!(app.world.get::<Lifetime>(fireball).is_none());
+        assert!(app.world.get::<FadeOut>(fireball).is_some());
+        // It can't hurt the player while fading
+        assert!(app.world.get::<ColliderDisabled>(fireball).is_some());
+
+        step(&mut app, 0.15);
+        let alpha = app.world.get::<Sprite>(fireball).unwrap().color.a();
+        assert!((alpha - 0.5).abs() < 1e-3, "alpha {alpha}");
+        step(&mut app, 0.3);
+        assert!(app.world.get_entity(fireball).is_none());
+    }
+
+    #[test]
+    fn fireballs_fade_out_after_their_last_bounce() {
+        let (mut app, fireball) = lifetime_app(GameTuning {
+            fireball_max_bounces: Some(2),
+            ..default()
+        });
+        app.world
+            .send_event(ScreenCollisionEvent { entity: fireball });
+        step(&mut app, 0.1);
+        assert_eq!(
+            app.world.get::<Lifetime>(fireball).unwrap().bounces_left,
+            Some(1)
+        );
+        // Time alone doesn't wear it out
+        step(&mut app, 100.0);
+        assert!(app.world.get::<FadeOut>(fireball).is_none());
+
+        app.world
+            .send_event(ScreenCollisionEvent { entity: fireball });
+        step(&mut app, 0.1);
+        assert!(app.world.get::<FadeOut>(fireball).is_some());
+    }
+}
@@ -0,0 +1,693 @@
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    actions::{update_action_state, Action, ActionState, InputBindings, BINDINGS_KEY},
+    difficulty::Difficulty,
+    gamepad::GamepadInput,
+    leaderboard::{format_date, save_name_entry, Leaderboard, NameEntry},
+    player::MovementSettings,
+    rng::GameRng,
+    score::Score,
+    state::{GameState, StateFlags},
+    stats::{format_run_time, RunClock, RunStats},
+    storage::ScoreStorage,
+    ui::GameData,
+};
+
+const TITLE_FONT_SIZE: f32 = 80.0;
+const TEXT_FONT_SIZE: f32 = 36.0;
+const ITEM_FONT_SIZE: f32 = 40.0;
+const MENU_BACKGROUND: Color = Color::rgba(0.1, 0.0, 0.15, 0.85);
+// Lets the frozen scene show through the pause overlay
+const PAUSE_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const NORMAL_ITEM: Color = Color::rgba(0.2, 0.2, 0.2, 0.8);
+const SELECTED_ITEM: Color = Color::rgba(0.75, 0.3, 0.05, 0.9);
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+/// On-screen button opening the pause menu, for devices without an Escape key.
+#[derive(Component)]
+pub struct PauseButton;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    Play,
+    Settings,
+    Quit,
+    Back,
+    CycleDifficulty,
+    CycleMovement,
+    Controls,
+    Leaderboard,
+    /// Waits for the next key or gamepad button pressed and binds it to the action.
+    Rebind(Action),
+    ResetBindings,
+    Resume,
+    Restart,
+    Retry,
+    QuitToMenu,
+    /// Saves the name typed for the leaderboard.
+    SubmitName,
+    /// Leaves the leaderboard without an entry for this run.
+    SkipName,
+}
+
+#[derive(Component)]
+pub struct MenuItem {
+    index: usize,
+    action: MenuAction,
+}
+
+/// Settings changed from the settings menu.
+#[derive(SystemParam)]
+pub struct MenuSettings<'w> {
+    difficulty: ResMut<'w, Difficulty>,
+    movement: ResMut<'w, MovementSettings>,
+    bindings: ResMut<'w, InputBindings>,
+    rebinding: ResMut<'w, PendingRebinding>,
+    storage: ResMut<'w, ScoreStorage>,
+    name_entry: Option<ResMut<'w, NameEntry>>,
+}
+
+/// Action of the controls menu waiting for its new key or button.
+#[derive(Resource, Default)]
+pub struct PendingRebinding(pub Option<Action>);
+
+/// Index of the highlighted item of the menu on screen.
+#[derive(Resource, Default)]
+pub struct MenuSelection(pub usize);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuSelection>()
+            .init_resource::<PendingRebinding>()
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnExit(GameState::MainMenu), despawn_menu)
+            .add_systems(OnEnter(GameState::Settings), spawn_settings_menu)
+            .add_systems(OnExit(GameState::Settings), despawn_menu)
+            .add_systems(OnEnter(GameState::Controls), spawn_controls_menu)
+            .add_systems(
+                OnExit(GameState::Controls),
+                (despawn_menu, cancel_rebinding),
+            )
+            .add_systems(
+                PreUpdate,
+                capture_binding
+                    .after(update_action_state)
+                    .run_if(in_state(GameState::Controls)),
+            )
+            .add_systems(OnEnter(GameState::Leaderboard), spawn_leaderboard_menu)
+            .add_systems(OnExit(GameState::Leaderboard), despawn_menu)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+            .add_systems(OnExit(GameState::Paused), despawn_menu)
+            .add_systems(OnExit(GameState::GameOver), despawn_menu)
+            .add_systems(
+                Update,
+                (
+                    // A name saved as the explosion ends must not bring its panel back
+                    spawn_game_over_menu.after(save_name_entry),
+                    retry_on_tap,
+                )
+                    .run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnEnter(GameState::InGame), spawn_pause_button)
+            .add_systems(OnExit(GameState::InGame), despawn_pause_button)
+            .add_systems(
+                Update,
+                pause_button_input.run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    menu_action_input,
+                    menu_pointer_input,
+                    highlight_menu_items,
+                    update_setting_labels.run_if(
+                        resource_changed::<Difficulty>
+                            .or_else(resource_changed::<MovementSettings>)
+                            .or_else(resource_changed::<InputBindings>)
+                            .or_else(resource_changed::<PendingRebinding>)
+                            .or_else(resource_exists_and_changed::<NameEntry>),
+                    ),
+                )
+                    .chain()
+                    .run_if(any_with_component::<MenuItem>),
+            );
+    }
+}
+
+/// Spawns a centered menu made of a title, some lines of text and the
+/// selectable `items`, which can be picked with the move and confirm actions or
+/// by clicking/tapping them.
+pub fn spawn_menu(
+    commands: &mut Commands,
+    selection: &mut MenuSelection,
+    background: Color,
+    title: &str,
+    lines: &[String],
+    items: &[(&str, MenuAction)],
+) {
+    selection.0 = 0;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: background.into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: TITLE_FONT_SIZE,
+                    color: Color::ORANGE,
+                    ..default()
+                },
+            ));
+            for line in lines {
+                parent.spawn(TextBundle::from_section(
+                    line.clone(),
+                    TextStyle {
+                        font_size: TEXT_FONT_SIZE,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+            }
+            for (index, (label, action)) in items.iter().enumerate() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                min_width: Val::Px(320.0),
+                                padding: UiRect::all(Val::Px(8.0)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: NORMAL_ITEM.into(),
+                            ..default()
+                        },
+                        MenuItem {
+                            index,
+                            action: *action,
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            *label,
+                            TextStyle {
+                                font_size: ITEM_FONT_SIZE,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn despawn_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    game_data: Res<GameData>,
+) {
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        MENU_BACKGROUND,
+        "DodgeFireBall",
+        &[
+            format!("High Score: {}", game_data.record),
+            format!("Best Time: {}", format_run_time(game_data.best_time)),
+        ],
+        &[
+            ("Play", MenuAction::Play),
+            ("Leaderboard", MenuAction::Leaderboard),
+            ("Settings", MenuAction::Settings),
+            ("Quit", MenuAction::Quit),
+        ],
+    );
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    difficulty: Res<Difficulty>,
+    movement: Res<MovementSettings>,
+) {
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        MENU_BACKGROUND,
+        "Settings",
+        &[],
+        &[
+            (&difficulty_label(&difficulty), MenuAction::CycleDifficulty),
+            (&movement_label(&movement), MenuAction::CycleMovement),
+            ("Controls", MenuAction::Controls),
+            ("Back", MenuAction::Back),
+        ],
+    );
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    bindings: Res<InputBindings>,
+    rebinding: Res<PendingRebinding>,
+) {
+    let labels: Vec<_> = Action::ALL
+        .iter()
+        .map(|action| binding_label(&bindings, &rebinding, *action))
+        .collect();
+    let mut items: Vec<_> = labels
+        .iter()
+        .zip(Action::ALL)
+        .map(|(label, action)| (label.as_str(), MenuAction::Rebind(action)))
+        .collect();
+    items.push(("Reset to defaults", MenuAction::ResetBindings));
+    items.push(("Back", MenuAction::Settings));
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        MENU_BACKGROUND,
+        "Controls",
+        &["Esc / Start cancels a rebinding".to_string()],
+        &items,
+    );
+}
+
+fn spawn_leaderboard_menu(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    leaderboard: Res<Leaderboard>,
+) {
+    let mut lines: Vec<_> = leaderboard
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            format!(
+                "{}. {}  {}  {}  {}  {}  seed {}",
+                index + 1,
+                entry.name,
+                entry.score,
+                format_run_time(entry.time_survived),
+                entry.difficulty.name(),
+                format_date(entry.date),
+                entry.seed
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No runs yet".to_string());
+    }
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        MENU_BACKGROUND,
+        "Leaderboard",
+        &lines,
+        &[("Back", MenuAction::Back)],
+    );
+}
+
+fn difficulty_label(difficulty: &Difficulty) -> String {
+    format!("Difficulty: {}", difficulty.preset.name())
+}
+
+fn movement_label(movement: &MovementSettings) -> String {
+    format!("Movement: {}", movement.mode.name())
+}
+
+fn binding_label(bindings: &InputBindings, rebinding: &PendingRebinding, action: Action) -> String {
+    if rebinding.0 == Some(action) {
+        return format!("{}: press a key or button", action.name());
+    }
+    let binding = bindings.get(action);
+    let inputs: Vec<_> = binding
+        .keys
+        .iter()
+        .map(|key| format!("{key:?}"))
+        .chain(binding.buttons.iter().map(|button| format!("{button:?}")))
+        .collect();
+    format!("{}: {}", action.name(), inputs.join(", "))
+}
+
+// Also the button saving the name, for players without a keyboard
+fn name_label(name_entry: &NameEntry) -> String {
+    format!("Save as: {}_", name_entry.name)
+}
+
+fn update_setting_labels(
+    item_query: Query<(&MenuItem, &Children)>,
+    mut text_query: Query<&mut Text>,
+    difficulty: Res<Difficulty>,
+    movement: Res<MovementSettings>,
+    bindings: Res<InputBindings>,
+    rebinding: Res<PendingRebinding>,
+    name_entry: Option<Res<NameEntry>>,
+) {
+    for (item, children) in item_query.iter() {
+        let label = match item.action {
+            MenuAction::CycleDifficulty => difficulty_label(&difficulty),
+            MenuAction::CycleMovement => movement_label(&movement),
+            MenuAction::Rebind(action) => binding_label(&bindings, &rebinding, action),
+            MenuAction::SubmitName => match &name_entry {
+                Some(name_entry) => name_label(name_entry),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands, mut selection: ResMut<MenuSelection>) {
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        PAUSE_BACKGROUND,
+        "Paused",
+        &[],
+        &[
+            ("Resume", MenuAction::Resume),
+            ("Restart", MenuAction::Restart),
+            ("Quit", MenuAction::QuitToMenu),
+        ],
+    );
+}
+
+// The panel waits for the explosion to be over, which is also when the run can
+// be restarted. A run making it to the leaderboard first asks for a name.
+#[allow(clippy::too_many_arguments)]
+fn spawn_game_over_menu(
+    mut commands: Commands,
+    mut selection: ResMut<MenuSelection>,
+    state_flags: Res<StateFlags>,
+    menu_query: Query<(), With<MenuRoot>>,
+    name_entry: Option<Res<NameEntry>>,
+    game_data: Res<GameData>,
+    score: Res<Score>,
+    clock: Res<RunClock>,
+    run_stats: Res<RunStats>,
+    rng: Res<GameRng>,
+) {
+    if !state_flags.explosion_ended || !menu_query.is_empty() {
+        return;
+    }
+    if let Some(name_entry) = name_entry {
+        spawn_menu(
+            &mut commands,
+            &mut selection,
+            PAUSE_BACKGROUND,
+            "New High Score!",
+            &[
+                format!("Score: {}", score.points),
+                "Type your name, then press Enter to save".to_string(),
+                "or Esc to skip".to_string(),
+            ],
+            &[
+                (&name_label(&name_entry), MenuAction::SubmitName),
+                ("Skip", MenuAction::SkipName),
+            ],
+        );
+        return;
+    }
+    let mut lines = vec![
+        format!("Score: {}", score.points),
+        format!(
+            "Near misses: {} (best combo x{})",
+            score.near_misses, score.best_multiplier
+        ),
+        format!("Fireballs survived: {}", game_data.n_balls),
+        format!("Time survived: {}", format_run_time(clock.elapsed())),
+        format!("Max fireball speed: {:.0}", run_stats.max_fireball_speed),
+        format!("Dashes: {}", run_stats.dashes),
+    ];
+    if run_stats.new_record {
+        lines.push("New record!".to_string());
+    }
+    if run_stats.new_best_time {
+        lines.push("New best time!".to_string());
+    }
+    lines.push(format!("Seed: {}", rng.seed()));
+    lines.push("Press Esc / tap to retry".to_string());
+    spawn_menu(
+        &mut commands,
+        &mut selection,
+        PAUSE_BACKGROUND,
+        "Game Over",
+        &lines,
+        &[
+            ("Retry", MenuAction::Retry),
+            ("Leaderboard", MenuAction::Leaderboard),
+            ("Main Menu", MenuAction::QuitToMenu),
+        ],
+    );
+}
+
+// A tap anywhere but on the panel buttons retries
+fn retry_on_tap(
+    touches: Res<Touches>,
+    state_flags: Res<StateFlags>,
+    name_entry: Option<Res<NameEntry>>,
+    item_query: Query<&Interaction, With<MenuItem>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if state_flags.explosion_ended
+        && name_entry.is_none()
+        && touches.any_just_pressed()
+        && item_query
+            .iter()
+            .all(|interaction| *interaction == Interaction::None)
+    {
+        next_state.set(GameState::InGame);
+    }
+}
+
+fn spawn_pause_button(mut commands: Commands) {
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.0),
+                    right: Val::Px(12.0),
+                    width: Val::Px(64.0),
+                    height: Val::Px(64.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_ITEM.into(),
+                ..default()
+            },
+            PauseButton,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                "II",
+                TextStyle {
+                    font_size: ITEM_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn despawn_pause_button(mut commands: Commands, query: Query<Entity, With<PauseButton>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn pause_button_input(
+    query: Query<&Interaction, (With<PauseButton>, Changed<Interaction>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn menu_action_input(
+    actions: Res<ActionState>,
+    query: Query<&MenuItem>,
+    mut selection: ResMut<MenuSelection>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut state_flags: ResMut<StateFlags>,
+    mut settings: MenuSettings,
+    mut app_exit_writer: EventWriter<AppExit>,
+) {
+    let n_items = query.iter().count();
+    if actions.just_pressed(Action::MoveDown) {
+        selection.0 = (selection.0 + 1) % n_items;
+    } else if actions.just_pressed(Action::MoveUp) {
+        selection.0 = (selection.0 + n_items - 1) % n_items;
+    } else if actions.just_pressed(Action::Confirm) {
+        if let Some(item) = query.iter().find(|item| item.index == selection.0) {
+            apply_menu_action(
+                item.action,
+                &mut next_state,
+                &mut state_flags,
+                &mut settings,
+                &mut app_exit_writer,
+            );
+        }
+    }
+}
+
+// Buttons get their `Interaction` from both the mouse and touches
+fn menu_pointer_input(
+    query: Query<(&Interaction, &MenuItem), Changed<Interaction>>,
+    mut selection: ResMut<MenuSelection>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut state_flags: ResMut<StateFlags>,
+    mut settings: MenuSettings,
+    mut app_exit_writer: EventWriter<AppExit>,
+) {
+    for (interaction, item) in query.iter() {
+        match interaction {
+            Interaction::Pressed => {
+                selection.0 = item.index;
+                apply_menu_action(
+                    item.action,
+                    &mut next_state,
+                    &mut state_flags,
+                    &mut settings,
+                    &mut app_exit_writer,
+                );
+            }
+            Interaction::Hovered => selection.0 = item.index,
+            Interaction::None => {}
+        }
+    }
+}
+
+fn highlight_menu_items(
+    mut query: Query<(&MenuItem, &mut BackgroundColor)>,
+    selection: Res<MenuSelection>,
+) {
+    for (item, mut background_color) in query.iter_mut() {
+        *background_color = if item.index == selection.0 {
+            SELECTED_ITEM.into()
+        } else {
+            NORMAL_ITEM.into()
+        };
+    }
+}
+
+fn apply_menu_action(
+    action: MenuAction,
+    next_state: &mut NextState<GameState>,
+    state_flags: &mut StateFlags,
+    settings: &mut MenuSettings,
+    app_exit_writer: &mut EventWriter<AppExit>,
+) {
+    match action {
+        MenuAction::Play | MenuAction::Resume | MenuAction::Retry => {
+            next_state.set(GameState::InGame)
+        }
+        MenuAction::Settings => next_state.set(GameState::Settings),
+        MenuAction::CycleDifficulty => {
+            settings.difficulty.preset = settings.difficulty.preset.next()
+        }
+        MenuAction::CycleMovement => settings.movement.mode = settings.movement.mode.next(),
+        MenuAction::Controls => next_state.set(GameState::Controls),
+        MenuAction::Leaderboard => next_state.set(GameState::Leaderboard),
+        MenuAction::Rebind(action) => settings.rebinding.0 = Some(action),
+        MenuAction::ResetBindings => {
+            *settings.bindings = InputBindings::default();
+            settings.rebinding.0 = None;
+            store_bindings(&mut settings.storage, &settings.bindings);
+        }
+        MenuAction::Back | MenuAction::QuitToMenu => next_state.set(GameState::MainMenu),
+        MenuAction::Restart => {
+            state_flags.restart_requested = true;
+            next_state.set(GameState::InGame);
+        }
+        MenuAction::Quit => {
+            app_exit_writer.send(AppExit);
+        }
+        MenuAction::SubmitName => {
+            if let Some(name_entry) = &mut settings.name_entry {
+                name_entry.submitted = true;
+            }
+        }
+        MenuAction::SkipName => {
+            if let Some(name_entry) = &mut settings.name_entry {
+                name_entry.skipped = true;
+            }
+        }
+    }
+}
+
+// Runs before the menu reads the actions, so the captured press doesn't also
+// navigate the menu.
+fn capture_binding(
+    mut rebinding: ResMut<PendingRebinding>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: GamepadInput,
+    mut actions: ResMut<ActionState>,
+    mut bindings: ResMut<InputBindings>,
+    mut storage: ResMut<ScoreStorage>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let key = keyboard_input.get_just_pressed().next().copied();
+    let button = gamepad_input.any_just_pressed();
+    match (key, button) {
+        (None, None) => return,
+        // Escape and Start cancel, so they can't be bound from here
+        (Some(KeyCode::Escape), _) | (_, Some(GamepadButtonType::Start)) => {}
+        (Some(key), _) => {
+            bindings.bind_key(action, key);
+            store_bindings(&mut storage, &bindings);
+        }
+        (None, Some(button)) => {
+            bindings.bind_button(action, button);
+            store_bindings(&mut storage, &bindings);
+        }
+    }
+    rebinding.0 = None;
+    actions.clear();
+}
+
+fn cancel_rebinding(mut rebinding: ResMut<PendingRebinding>) {
+    rebinding.0 = None;
+}
+
+fn store_bindings(storage: &mut ScoreStorage, bindings: &InputBindings) {
+    if let Err(error) = storage.set(BINDINGS_KEY, bindings) {
+        error!("failed to store input bindings: {error}");
+    }
+}
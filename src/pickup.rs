@@ -0,0 +1,358 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*, window::PrimaryWindow};
+use bevy_rapier2d::prelude::*;
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
+
+use crate::{
+    collision::pickup_groups,
+    explosion::spawn_mini_explosion,
+    fireball::Fireball,
+    graphics::SceneAssets,
+    player::{Player, PLAYER_PIXELS},
+    rng::GameRng,
+    schedule::InGameSet,
+    state::{OnRunEnd, OnRunStart},
+    tuning::GameTuning,
+};
+
+// Keeps pickups away from the edges of the window
+const SPAWN_MARGIN: f32 = 100.0;
+// Candidate points tried for a new pickup, the one farthest from any fireball wins
+const SPAWN_CANDIDATES: usize = 16;
+const PICKUP_SIZE: f32 = 28.0;
+// Pickups blink during their last seconds
+const PICKUP_BLINK_TIME: f32 = 2.0;
+const PICKUP_BLINK_RATE: f32 = 6.0;
+// Radians per second
+const PICKUP_SPIN: f32 = 2.0;
+const SHIELD_RADIUS: f32 = PLAYER_PIXELS * 0.8;
+const SHIELD_COLOR: Color = Color::rgba(0.4, 0.8, 1.0, 0.7);
+
+/// Power-up waiting in the arena until the player runs into it.
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    timer: Timer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickupKind {
+    /// Absorbs the next hit.
+    Shield,
+    /// Slows every fireball down for a while.
+    SlowMotion,
+    /// Makes the player smaller for a while.
+    Shrink,
+    /// Destroys the fireballs around the player.
+    Bomb,
+}
+
+/// Effects of the collected pickups in the current run.
+#[derive(Resource, Default)]
+pub struct PowerUps {
+    pub shield: bool,
+    pub slow_motion: Option<Timer>,
+    pub shrink: Option<Timer>,
+}
+
+/// Fireball slowed down by a slow motion, `factor` is applied to its velocity.
+#[derive(Component)]
+pub struct Slowed {
+    pub factor: f32,
+}
+
+#[derive(Resource, Default)]
+struct PickupSpawner {
+    timer: Timer,
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUps>()
+            .init_resource::<PickupSpawner>()
+            .add_systems(OnRunStart, (reset_pickup_spawner, reset_power_ups))
+            .add_systems(OnRunEnd, (despawn_pickups, reset_power_ups))
+            .add_systems(
+                Update,
+                (
+                    spawn_pickups,
+                    update_pickups,
+                    collect_pickups,
+                    update_power_ups,
+                    apply_slow_motion,
+                )
+                    .chain()
+                    .in_set(InGameSet::EntityUpdates),
+            );
+        // The shield is drawn with gizmos, which only exist with a renderer
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_shield);
+        }
+    }
+}
+
+impl PickupKind {
+    pub const ALL: [PickupKind; 4] = [
+        PickupKind::Shield,
+        PickupKind::SlowMotion,
+        PickupKind::Shrink,
+        PickupKind::Bomb,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PickupKind::Shield => "Shield",
+            PickupKind::SlowMotion => "Slow motion",
+            PickupKind::Shrink => "Shrink",
+            PickupKind::Bomb => "Bomb",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            PickupKind::Shield => Color::rgb(0.4, 0.8, 1.0),
+            PickupKind::SlowMotion => Color::rgb(0.7, 0.5, 1.0),
+            PickupKind::Shrink => Color::rgb(0.5, 1.0, 0.5),
+            PickupKind::Bomb => Color::rgb(1.0, 0.3, 0.2),
+        }
+    }
+}
+
+impl PowerUps {
+    /// Timed effects in progress with their seconds left.
+    pub fn timed_effects(&self) -> impl Iterator<Item = (PickupKind, f32)> + '_ {
+        [
+            (PickupKind::SlowMotion, &self.slow_motion),
+            (PickupKind::Shrink, &self.shrink),
+        ]
+        .into_iter()
+        .filter_map(|(kind, timer)| Some((kind, timer.as_ref()?.remaining_secs())))
+    }
+}
+
+fn reset_pickup_spawner(mut spawner: ResMut<PickupSpawner>, tuning: Res<GameTuning>) {
+    spawner.timer = Timer::from_seconds(tuning.pickup_spawn_time, TimerMode::Repeating);
+}
+
+fn reset_power_ups(mut power_ups: ResMut<PowerUps>) {
+    *power_ups = PowerUps::default();
+}
+
+fn spawn_pickups(
+    mut commands: Commands,
+    mut spawner: ResMut<PickupSpawner>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    fireball_query: Query<&Transform, With<Fireball>>,
+    mut rng: ResMut<GameRng>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
+) {
+    spawner.timer.tick(time.delta());
+    if !spawner.timer.just_finished() {
+        return;
+    }
+    let window = window_query.get_single().unwrap();
+    let fireballs: Vec<Vec2> = fireball_query
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect();
+    let point = pick_pickup_point(
+        &mut rng,
+        Vec2::new(window.width(), window.height()) / 2.0 - SPAWN_MARGIN,
+        &fireballs,
+    );
+    let kind = PickupKind::ALL[rng.gen_range(0..PickupKind::ALL.len())];
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(PICKUP_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(point.extend(1.0)),
+            ..default()
+        },
+        Pickup {
+            kind,
+            timer: Timer::from_seconds(tuning.pickup_lifetime, TimerMode::Once),
+        },
+        Collider::ball(PICKUP_SIZE / 2.0),
+        Sensor,
+        pickup_groups(),
+        ActiveEvents::COLLISION_EVENTS,
+    ));
+}
+
+/// Picks the random point inside `half_extents` farthest from any of `fireballs`.
+fn pick_pickup_point(rng: &mut GameRng, half_extents: Vec2, fireballs: &[Vec2]) -> Vec2 {
+    let between_width = Uniform::from(-half_extents.x..half_extents.x);
+    let between_height = Uniform::from(-half_extents.y..half_extents.y);
+    let clearance = |point: Vec2| {
+        fireballs
+            .iter()
+            .map(|fireball| fireball.distance(point))
+            .fold(f32::INFINITY, f32::min)
+    };
+    (0..SPAWN_CANDIDATES)
+        .map(|_| Vec2::new(between_width.sample(rng), between_height.sample(rng)))
+        .max_by(|a, b| clearance(*a).total_cmp(&clearance(*b)))
+        .unwrap()
+}
+
+/// Spins the pickups, blinks them before they expire and despawns the expired ones.
+fn update_pickups(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Pickup, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut pickup, mut transform, mut sprite) in query.iter_mut() {
+        pickup.timer.tick(time.delta());
+        if pickup.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.rotate_z(PICKUP_SPIN * time.delta_seconds());
+        let remaining = pickup.timer.remaining_secs();
+        let blink_on = remaining > PICKUP_BLINK_TIME
+            || ((remaining * PICKUP_BLINK_RATE) as u32).is_multiple_of(2);
+        sprite.color.set_a(if blink_on { 1.0 } else { 0.2 });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_pickups(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<CollisionEvent>,
+    mut player_query: Query<(Entity, &Transform, &mut Collider, &mut Sprite), With<Player>>,
+    pickup_query: Query<&Pickup>,
+    fireball_query: Query<(Entity, &Transform), With<Fireball>>,
+    mut power_ups: ResMut<PowerUps>,
+    scene_assets: Res<SceneAssets>,
+    tuning: Res<GameTuning>,
+) {
+    let Ok((player_entity, player_transform, mut collider, mut sprite)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+    for collision in collision_event_reader.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision else {
+            continue;
+        };
+        let pickup_entity = if *e1 == player_entity {
+            *e2
+        } else if *e2 == player_entity {
+            *e1
+        } else {
+            continue;
+        };
+        let Ok(pickup) = pickup_query.get(pickup_entity) else {
+            continue;
+        };
+        commands.entity(pickup_entity).despawn_recursive();
+        match pickup.kind {
+            PickupKind::Shield => power_ups.shield = true,
+            PickupKind::SlowMotion => {
+                power_ups.slow_motion = Some(Timer::from_seconds(
+                    tuning.slow_motion_time,
+                    TimerMode::Once,
+                ));
+            }
+            PickupKind::Shrink => {
+                power_ups.shrink = Some(Timer::from_seconds(tuning.shrink_time, TimerMode::Once));
+                resize_player(&mut collider, &mut sprite, tuning.shrink_factor);
+            }
+            PickupKind::Bomb => {
+                let center = player_transform.translation.xy();
+                for (fireball, transform) in fireball_query.iter() {
+                    if transform.translation.xy().distance(center) <= tuning.bomb_radius {
+                        commands.entity(fireball).despawn_recursive();
+                        spawn_mini_explosion(&mut commands, &scene_assets, transform.translation);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn update_power_ups(
+    mut power_ups: ResMut<PowerUps>,
+    mut player_query: Query<(&mut Collider, &mut Sprite), With<Player>>,
+    time: Res<Time>,
+) {
+    let PowerUps {
+        slow_motion,
+        shrink,
+        ..
+    } = &mut *power_ups;
+    if let Some(timer) = slow_motion {
+        if timer.tick(time.delta()).finished() {
+            *slow_motion = None;
+        }
+    }
+    if let Some(timer) = shrink {
+        if timer.tick(time.delta()).finished() {
+            *shrink = None;
+            if let Ok((mut collider, mut sprite)) = player_query.get_single_mut() {
+                resize_player(&mut collider, &mut sprite, 1.0);
+            }
+        }
+    }
+}
+
+// The collider is replaced rather than scaled, rapier keeps the scale of a
+// collider in sync with its transform
+fn resize_player(collider: &mut Collider, sprite: &mut Sprite, factor: f32) {
+    let size = PLAYER_PIXELS * factor;
+    *collider = Collider::cuboid(size / 2.0, size / 2.0);
+    sprite.custom_size = (factor != 1.0).then_some(Vec2::splat(size));
+}
+
+/// Slows down the fireballs while a slow motion lasts, including the ones
+/// spawned meanwhile, and gives them back their speed after.
+fn apply_slow_motion(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Velocity, Option<&Slowed>), With<Fireball>>,
+    power_ups: Res<PowerUps>,
+    tuning: Res<GameTuning>,
+) {
+    let active = power_ups.slow_motion.is_some();
+    for (entity, mut velocity, slowed) in query.iter_mut() {
+        match (active, slowed) {
+            (true, None) => {
+                velocity.linvel *= tuning.slow_motion_factor;
+                commands.entity(entity).insert(Slowed {
+                    factor: tuning.slow_motion_factor,
+                });
+            }
+            (false, Some(slowed)) => {
+                velocity.linvel /= slowed.factor;
+                commands.entity(entity).remove::<Slowed>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn despawn_pickups(mut commands: Commands, query: Query<Entity, With<Pickup>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn draw_shield(
+    mut gizmos: Gizmos,
+    power_ups: Res<PowerUps>,
+    query: Query<&Transform, With<Player>>,
+) {
+    if !power_ups.shield {
+        return;
+    }
+    if let Ok(transform) = query.get_single() {
+        gizmos.circle_2d(transform.translation.xy(), SHIELD_RADIUS, SHIELD_COLOR);
+    }
+}
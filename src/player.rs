@@ -1,23 +1,37 @@
+use std::f32::consts::FRAC_PI_4;
+
 use bevy::prelude::*;
-#[cfg(target_os = "android")]
-use bevy::window::PrimaryWindow;
 use bevy_rapier2d::{pipeline::CollisionEvent, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    actions::ActionState,
+    collision::{invulnerable_player_groups, player_groups, Hazard},
+    config::GameConfig,
+    dash::Dashing,
     graphics::{AnimationTimer, SceneAssets},
+    pickup::PowerUps,
     schedule::InGameSet,
-    screen_bound_collision_detection::{handle_screen_bound_collisions, ScreenCollisionEvent},
-    state::GameState,
+    screen_bound_collision_detection::ScreenCollisionEvent,
+    state::{GameState, OnRunEnd, OnRunStart},
+    tuning::GameTuning,
+    ui::GameData,
 };
 
-const PLAYER_SCALE: f32 = 1.0;
-const INITIAL_VELOCITY: f32 = 100.0;
 const INITIAL_DIRECTION: PlayerDirection = PlayerDirection::Down;
+// Seconds between two blinks of an invulnerable player
+const BLINK_PERIOD: f32 = 0.1;
 pub const PLAYER_PIXELS: f32 = 64.0;
 
 #[derive(Component)]
 pub struct Player;
 
+/// Set after a hit: hazards go through the player until the timer ends.
+#[derive(Component)]
+pub struct Invulnerable {
+    timer: Timer,
+}
+
 #[derive(Resource)]
 pub struct PlayerController {
     pub enabled: bool,
@@ -25,7 +39,23 @@ pub struct PlayerController {
 
 pub struct PlayerPlugin;
 
-#[derive(Component, Default, Clone)]
+/// How input turns into the velocity of the player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MovementMode {
+    /// Any direction, with the acceleration and deceleration of the tuning.
+    #[default]
+    Analog,
+    /// One of 8 directions, at full speed right away.
+    EightWay,
+}
+
+#[derive(Resource, Default)]
+pub struct MovementSettings {
+    pub mode: MovementMode,
+}
+
+/// The 8 directions of the 8-way movement.
+#[derive(Default, Clone)]
 pub enum PlayerDirection {
     Up,
     #[default]
@@ -40,16 +70,22 @@ pub enum PlayerDirection {
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
+        let mode = app
+            .world
+            .get_resource::<GameConfig>()
+            .map(|config| config.movement)
+            .unwrap_or_default();
         app.init_resource::<PlayerController>()
-            .add_systems(Startup, spawn_player)
-            .add_systems(OnExit(GameState::GameOver), spawn_player)
+            .insert_resource(MovementSettings { mode })
+            .add_systems(OnRunStart, spawn_player)
+            .add_systems(OnRunEnd, despawn_player)
             .add_systems(Update, (player_controller).in_set(InGameSet::UserInput))
             .add_systems(
                 Update,
                 (
-                    check_player_death,
+                    handle_player_hits,
+                    update_invulnerability,
                     animate_player,
-                    handle_screen_bound_collisions::<Player>,
                     apply_screen_collision,
                 )
                     .chain()
@@ -81,31 +117,52 @@ impl PlayerDirection {
             }
         }
     }
-    fn from_direction2d(vel: Vec2) -> PlayerDirection {
-        let direction = Direction2d::new_unchecked(vel.normalize());
-        if direction == Direction2d::new_unchecked(Vec2::new(0.0, 1.0)) {
-            PlayerDirection::Up
-        } else if direction == Direction2d::new_unchecked(Vec2::new(0.0, -1.0)) {
-            PlayerDirection::Down
-        } else if direction == Direction2d::new_unchecked(Vec2::new(-1.0, 0.0)) {
-            PlayerDirection::Left
-        } else if direction == Direction2d::new_unchecked(Vec2::new(1.0, 0.0)) {
-            PlayerDirection::Right
-        } else if direction == Direction2d::new_unchecked(Vec2::new(-1.0, 1.0).normalize()) {
-            PlayerDirection::UpLeft
-        } else if direction == Direction2d::new_unchecked(Vec2::new(1.0, 1.0).normalize()) {
-            PlayerDirection::UpRight
-        } else if direction == Direction2d::new_unchecked(Vec2::new(-1.0, -1.0).normalize()) {
-            PlayerDirection::DownLeft
-        } else if direction == Direction2d::new_unchecked(Vec2::new(1.0, -1.0).normalize()) {
-            PlayerDirection::DownRight
-        } else {
-            PlayerDirection::Down
+    /// Closest of the 8 directions to `direction`.
+    fn snap(direction: Vec2) -> PlayerDirection {
+        // Octants counted counterclockwise from the right
+        match (direction.y.atan2(direction.x) / FRAC_PI_4).round() as i32 {
+            0 => PlayerDirection::Right,
+            1 => PlayerDirection::UpRight,
+            2 => PlayerDirection::Up,
+            3 => PlayerDirection::UpLeft,
+            4 | -4 => PlayerDirection::Left,
+            -3 => PlayerDirection::DownLeft,
+            -1 => PlayerDirection::DownRight,
+            _ => PlayerDirection::Down,
         }
     }
 }
 
-pub fn spawn_player(mut commands: Commands, scene_assets: Res<SceneAssets>) {
+impl MovementMode {
+    pub const ALL: [MovementMode; 2] = [MovementMode::Analog, MovementMode::EightWay];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MovementMode::Analog => "Analog",
+            MovementMode::EightWay => "8-way",
+        }
+    }
+
+    /// Case-insensitive inverse of [`MovementMode::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+pub fn spawn_player(
+    mut commands: Commands,
+    scene_assets: Res<SceneAssets>,
+    mut game_data: ResMut<GameData>,
+    tuning: Res<GameTuning>,
+) {
+    game_data.lives = tuning.player_lives;
     commands
         .spawn((
             SpriteSheetBundle {
@@ -129,7 +186,7 @@ pub fn spawn_player(mut commands: Commands, scene_assets: Res<SceneAssets>) {
                 },
                 transform: Transform {
                     translation: Vec3::new(0.0, 0.0, 0.0),
-                    scale: Vec2::new(PLAYER_SCALE, 0.0).xxy(),
+                    scale: Vec2::new(tuning.player_scale, 0.0).xxy(),
                     ..default()
                 },
                 ..default()
@@ -150,12 +207,11 @@ pub fn spawn_player(mut commands: Commands, scene_assets: Res<SceneAssets>) {
             },
             Player,
             RigidBody::Dynamic,
-            INITIAL_DIRECTION,
         ))
         .insert(Velocity {
             linvel: INITIAL_DIRECTION
                 .to_direction2d()
-                .rotate(Vec2::new(INITIAL_VELOCITY, 0.0)),
+                .rotate(Vec2::new(tuning.initial_velocity, 0.0)),
             angvel: 0.0,
         })
         .insert(Damping {
@@ -164,6 +220,7 @@ pub fn spawn_player(mut commands: Commands, scene_assets: Res<SceneAssets>) {
         })
         .insert(GravityScale(0.0))
         .insert(Collider::cuboid(PLAYER_PIXELS / 2.0, PLAYER_PIXELS / 2.0))
+        .insert(player_groups())
         .insert(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC)
         .insert(ActiveEvents::COLLISION_EVENTS);
     // .insert(Friction::coefficient(0.0))
@@ -172,166 +229,311 @@ pub fn spawn_player(mut commands: Commands, scene_assets: Res<SceneAssets>) {
 
 fn animate_player(
     time: Res<Time>,
-    mut query: Query<(&mut AnimationTimer, &mut TextureAtlas, &PlayerDirection), With<Player>>,
+    mut query: Query<(&mut AnimationTimer, &mut TextureAtlas, &Velocity), With<Player>>,
 ) {
-    let (mut timer, mut atlas, direction) = query.get_single_mut().unwrap();
+    let (mut timer, mut atlas, velocity) = query.get_single_mut().unwrap();
     timer.tick(time.delta());
     if timer.just_finished() {
-        match direction {
-            PlayerDirection::Up => atlas.index = (atlas.index + 1) % 4 + 4 * 3,
-            PlayerDirection::Down => atlas.index = (atlas.index + 1) % 4,
-            PlayerDirection::Left | PlayerDirection::UpLeft | PlayerDirection::DownLeft => {
-                atlas.index = (atlas.index + 1) % 4 + 4
-            }
-            PlayerDirection::Right | PlayerDirection::UpRight | PlayerDirection::DownRight => {
-                atlas.index = (atlas.index + 1) % 4 + 4 * 2
-            }
+        atlas.index = (atlas.index + 1) % 4 + 4 * animation_row(velocity.linvel);
+    }
+}
+
+// The sheet rows face down, left, right and up, picked by the dominant axis
+fn animation_row(velocity: Vec2) -> usize {
+    if velocity.x != 0.0 && velocity.x.abs() >= velocity.y.abs() {
+        if velocity.x < 0.0 {
+            1
+        } else {
+            2
         }
+    } else if velocity.y > 0.0 {
+        3
+    } else {
+        0
     }
 }
 
-#[cfg(not(target_os = "android"))]
-fn player_controller(
-    mut query: Query<(&mut PlayerDirection, &mut Velocity), With<Player>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+pub fn player_controller(
+    // A dash keeps its heading until it ends
+    mut query: Query<&mut Velocity, (With<Player>, Without<Dashing>)>,
+    actions: Res<ActionState>,
     mut player_controller: ResMut<PlayerController>,
+    movement: Res<MovementSettings>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
 ) {
-    let Ok((mut player_direction, mut velocity)) = query.get_single_mut() else {
+    let Ok(mut velocity) = query.get_single_mut() else {
         return;
     };
-    if player_controller.enabled {
-        let mut new_direction = None;
-        if keyboard_input.pressed(KeyCode::ArrowLeft) {
-            if keyboard_input.pressed(KeyCode::ArrowDown) {
-                new_direction = Some(PlayerDirection::DownLeft);
-            } else if keyboard_input.pressed(KeyCode::ArrowUp) {
-                new_direction = Some(PlayerDirection::UpLeft);
-            } else {
-                new_direction = Some(PlayerDirection::Left);
-            }
-        } else if keyboard_input.pressed(KeyCode::ArrowRight) {
-            if keyboard_input.pressed(KeyCode::ArrowDown) {
-                new_direction = Some(PlayerDirection::DownRight);
-            } else if keyboard_input.pressed(KeyCode::ArrowUp) {
-                new_direction = Some(PlayerDirection::UpRight);
-            } else {
-                new_direction = Some(PlayerDirection::Right);
-            }
-        } else if keyboard_input.pressed(KeyCode::ArrowUp) {
-            if keyboard_input.pressed(KeyCode::ArrowLeft) {
-                new_direction = Some(PlayerDirection::UpLeft);
-            } else if keyboard_input.pressed(KeyCode::ArrowRight) {
-                new_direction = Some(PlayerDirection::UpRight);
-            } else {
-                new_direction = Some(PlayerDirection::Up);
-            }
-        } else if keyboard_input.pressed(KeyCode::ArrowDown) {
-            if keyboard_input.pressed(KeyCode::ArrowLeft) {
-                new_direction = Some(PlayerDirection::DownLeft);
-            } else if keyboard_input.pressed(KeyCode::ArrowRight) {
-                new_direction = Some(PlayerDirection::DownRight);
-            } else {
-                new_direction = Some(PlayerDirection::Down);
-            }
-        }
-        if let Some(direction) = new_direction {
-            *player_direction = direction.clone();
-            *velocity = Velocity {
-                linvel: direction
-                    .to_direction2d()
-                    .rotate(Vec2::new(INITIAL_VELOCITY, 0.0)),
-                angvel: 0.0,
-            };
-        }
-    } else {
-        if !keyboard_input.pressed(KeyCode::ArrowLeft)
-            && !keyboard_input.pressed(KeyCode::ArrowRight)
-            && !keyboard_input.pressed(KeyCode::ArrowUp)
-            && !keyboard_input.pressed(KeyCode::ArrowDown)
-        {
-            player_controller.enabled = true;
+    let direction = actions.movement();
+    // A finger rests on the virtual joystick for the whole run, so steering
+    // with it doesn't wait for the input to be released after a bounce
+    if player_controller.enabled || actions.joystick {
+        if let Some(direction) = direction {
+            steer_player(
+                &mut velocity,
+                direction,
+                movement.mode,
+                &tuning,
+                time.delta_seconds(),
+            );
         }
+    } else if direction.is_none() {
+        player_controller.enabled = true;
     }
 }
 
-#[cfg(target_os = "android")]
-fn player_controller(
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut query: Query<(&mut PlayerDirection, &Transform, &mut Velocity), With<Player>>,
-    mut touch_events: EventReader<TouchInput>,
+/// Turns the player toward `direction` at full speed. 8-way movement snaps the
+/// direction and applies at once, analog movement follows the acceleration and
+/// deceleration of the tuning when set.
+pub fn steer_player(
+    velocity: &mut Velocity,
+    direction: Vec2,
+    mode: MovementMode,
+    tuning: &GameTuning,
+    delta_seconds: f32,
 ) {
-    let Ok((mut player_direction, player_transform, mut velocity)) = query.get_single_mut() else {
-        return;
+    let target = match mode {
+        MovementMode::EightWay => {
+            *PlayerDirection::snap(direction).to_direction2d() * tuning.initial_velocity
+        }
+        MovementMode::Analog => direction.normalize_or_zero() * tuning.initial_velocity,
     };
-    let window = window_query.get_single().unwrap();
-    let height = window.height() / 2.0;
-    let width = window.width() / 2.0;
-    let screen_center = Vec2::new(width, height);
-    let zero_vec = Vec2::new(-1.0, 0.0);
-    for event in touch_events.read() {
-        let new_direction_vec = screen_center - event.position;
-        // info!(
-        //     "{}",
-        //     new_direction_vec
-        //         .angle_between(zero_vec)
-        //         .to_degrees()
-        // );
-        let direction = match new_direction_vec.angle_between(zero_vec).to_degrees() {
-            -22.5..=22.5 => PlayerDirection::Right,
-            22.5..=67.5 => PlayerDirection::UpRight,
-            67.5..=112.5 => PlayerDirection::Up,
-            112.5..=157.5 => PlayerDirection::UpLeft,
-            157.5..=180.0 | -180.0..=-157.5 => PlayerDirection::Left,
-            -157.5..=-112.5 => PlayerDirection::DownLeft,
-            -112.5..=-67.5 => PlayerDirection::Down,
-            -67.5..=-22.5 => PlayerDirection::DownRight,
-            _ => PlayerDirection::Down,
-        };
-        *player_direction = direction.clone();
-        *velocity = Velocity {
-            linvel: direction
-                .to_direction2d()
-                .rotate(Vec2::new(INITIAL_VELOCITY, 0.0)),
-            angvel: 0.0,
-        };
-    }
+    // Slowing down only happens when faster than the target, e.g. after a knockback
+    let rate = if velocity.linvel.dot(target) < target.length_squared() {
+        tuning.player_acceleration
+    } else {
+        tuning.player_deceleration
+    };
+    velocity.linvel = match (mode, rate) {
+        (MovementMode::Analog, Some(rate)) => {
+            let step = rate * delta_seconds;
+            let delta = target - velocity.linvel;
+            if delta.length() <= step {
+                target
+            } else {
+                velocity.linvel + delta.normalize() * step
+            }
+        }
+        _ => target,
+    };
+    velocity.angvel = 0.0;
 }
 
 fn apply_screen_collision(
     mut collision_event_reader: EventReader<ScreenCollisionEvent>,
     mut player_controller: ResMut<PlayerController>,
-    mut query: Query<(Entity, &mut PlayerDirection, &Velocity), With<Player>>,
+    query: Query<Entity, With<Player>>,
 ) {
-    let (player_entity, mut player_direction, player_velocity) = query.get_single_mut().unwrap();
+    let player_entity = query.get_single().unwrap();
     for &ScreenCollisionEvent { entity } in collision_event_reader.read() {
         if entity == player_entity {
             player_controller.enabled = false;
-            *player_direction = PlayerDirection::from_direction2d(player_velocity.linvel);
         }
     }
 }
 
-fn check_player_death(
+// Only the last hit ends the run, the others knock the player back
+#[allow(clippy::too_many_arguments)]
+fn handle_player_hits(
+    mut commands: Commands,
     mut collision_event_reader: EventReader<CollisionEvent>,
-    query: Query<Entity, With<Player>>,
+    mut query: Query<(Entity, &Transform, &mut Velocity), With<Player>>,
+    invulnerable_query: Query<(), With<Invulnerable>>,
+    dashing_query: Query<(), With<Dashing>>,
+    hazard_query: Query<&Transform, With<Hazard>>,
+    mut game_data: ResMut<GameData>,
+    mut power_ups: ResMut<PowerUps>,
+    mut player_controller: ResMut<PlayerController>,
     mut next_state: ResMut<NextState<GameState>>,
+    tuning: Res<GameTuning>,
 ) {
-    let Ok(player_entity) = query.get_single() else {
+    let Ok((player_entity, player_transform, mut velocity)) = query.get_single_mut() else {
         return;
     };
+    let mut invulnerable =
+        invulnerable_query.contains(player_entity) || dashing_query.contains(player_entity);
     for collision in collision_event_reader.read() {
-        match collision {
-            CollisionEvent::Started(e1, e2, _) => {
-                if e1 == &player_entity || e2 == &player_entity {
-                    next_state.set(GameState::GameOver);
-                }
-            }
-            CollisionEvent::Stopped(_e1, _e2, _) => {}
+        let CollisionEvent::Started(e1, e2, _) = collision else {
+            continue;
+        };
+        let hazard = if *e1 == player_entity {
+            *e2
+        } else if *e2 == player_entity {
+            *e1
+        } else {
+            continue;
+        };
+        let Ok(hazard_transform) = hazard_query.get(hazard) else {
+            continue;
+        };
+        if invulnerable {
+            continue;
+        }
+        let invulnerability = (
+            Invulnerable {
+                timer: Timer::from_seconds(tuning.invulnerability_time, TimerMode::Once),
+            },
+            invulnerable_player_groups(),
+        );
+        // A shield takes the hit in place of a life, without any knockback
+        if power_ups.shield {
+            power_ups.shield = false;
+            commands.entity(player_entity).insert(invulnerability);
+            invulnerable = true;
+            continue;
+        }
+        game_data.lives = game_data.lives.saturating_sub(1);
+        if game_data.lives == 0 {
+            next_state.set(GameState::GameOver);
+            return;
         }
+        let away = (player_transform.translation.xy() - hazard_transform.translation.xy())
+            .try_normalize()
+            .unwrap_or(-velocity.linvel.normalize_or_zero());
+        velocity.linvel = away * tuning.knockback_speed;
+        // Like a wall bounce, the player has to press a key again
+        player_controller.enabled = false;
+        commands.entity(player_entity).insert(invulnerability);
+        invulnerable = true;
+    }
+}
+
+fn update_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility, &mut Velocity), With<Player>>,
+    dashing_query: Query<(), With<Dashing>>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
+) {
+    let Ok((entity, mut invulnerable, mut visibility, mut velocity)) = query.get_single_mut()
+    else {
+        return;
+    };
+    invulnerable.timer.tick(time.delta());
+    if invulnerable.timer.finished() {
+        *visibility = Visibility::Inherited;
+        commands.entity(entity).remove::<Invulnerable>();
+        // A dash in progress restores the speed and groups itself when it ends
+        if !dashing_query.contains(entity) {
+            // Back from the knockback to the usual speed
+            velocity.linvel = velocity.linvel.normalize_or_zero() * tuning.initial_velocity;
+            commands.entity(entity).insert(player_groups());
+        }
+    } else if ((invulnerable.timer.elapsed_secs() / BLINK_PERIOD) as u32).is_multiple_of(2) {
+        *visibility = Visibility::Hidden;
+    } else {
+        *visibility = Visibility::Inherited;
     }
 }
 
 pub fn despawn_player(mut commands: Commands, query: Query<Entity, With<Player>>) {
-    let player_entity = query.get_single().unwrap();
-    commands.entity(player_entity).despawn_recursive();
+    for player_entity in query.iter() {
+        commands.entity(player_entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA: f32 = 0.1;
+
+    fn tuning(acceleration: Option<f32>, deceleration: Option<f32>) -> GameTuning {
+        GameTuning {
+            initial_velocity: 100.0,
+            player_acceleration: acceleration,
+            player_deceleration: deceleration,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn eight_way_snaps_at_full_speed_at_once() {
+        let tuning = tuning(Some(200.0), Some(200.0));
+        let mut velocity = Velocity::zero();
+        steer_player(
+            &mut velocity,
+            Vec2::new(1.0, 0.3),
+            MovementMode::EightWay,
+            &tuning,
+            DELTA,
+        );
+        assert_eq!(velocity.linvel, Vec2::new(100.0, 0.0));
+        steer_player(
+            &mut velocity,
+            Vec2::new(-1.0, 0.9),
+            MovementMode::EightWay,
+            &tuning,
+            DELTA,
+        );
+        assert!(velocity
+            .linvel
+            .abs_diff_eq(Vec2::new(-1.0, 1.0).normalize() * 100.0, 1e-4));
+    }
+
+    #[test]
+    fn analog_turns_instantly_without_acceleration() {
+        let mut velocity = Velocity::zero();
+        let direction = Vec2::new(3.0, 4.0);
+        steer_player(
+            &mut velocity,
+            direction,
+            MovementMode::Analog,
+            &tuning(None, None),
+            DELTA,
+        );
+        assert!(velocity.linvel.abs_diff_eq(Vec2::new(60.0, 80.0), 1e-4));
+    }
+
+    #[test]
+    fn analog_follows_the_acceleration_and_deceleration() {
+        let tuning = tuning(Some(200.0), Some(100.0));
+        let mut velocity = Velocity::zero();
+        steer_player(&mut velocity, Vec2::X, MovementMode::Analog, &tuning, DELTA);
+        assert!(velocity.linvel.abs_diff_eq(Vec2::new(20.0, 0.0), 1e-4));
+        for _ in 0..10 {
+            steer_player(&mut velocity, Vec2::X, MovementMode::Analog, &tuning, DELTA);
+        }
+        assert_eq!(velocity.linvel, Vec2::new(100.0, 0.0));
+
+        // Knocked back faster than the target
+        velocity.linvel = Vec2::new(300.0, 0.0);
+        steer_player(&mut velocity, Vec2::X, MovementMode::Analog, &tuning, DELTA);
+        assert!(velocity.linvel.abs_diff_eq(Vec2::new(290.0, 0.0), 1e-4));
+    }
+
+    #[test]
+    fn rows_follow_the_dominant_axis() {
+        assert_eq!(animation_row(Vec2::ZERO), 0);
+        assert_eq!(animation_row(Vec2::new(1.0, -5.0)), 0);
+        assert_eq!(animation_row(Vec2::new(-5.0, 2.0)), 1);
+        assert_eq!(animation_row(Vec2::new(5.0, -2.0)), 2);
+        // Diagonals face sideways
+        assert_eq!(animation_row(Vec2::new(5.0, 5.0)), 2);
+        assert_eq!(animation_row(Vec2::new(1.0, 5.0)), 3);
+    }
+
+    #[test]
+    fn only_the_joystick_steers_through_a_bounce() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ActionState>()
+            .init_resource::<MovementSettings>()
+            .insert_resource(PlayerController { enabled: false })
+            .insert_resource(tuning(None, None))
+            .add_systems(Update, player_controller);
+        let player = app.world.spawn((Player, Velocity::zero())).id();
+        let velocity = |app: &App| app.world.get::<Velocity>(player).unwrap().linvel;
+
+        // A held stick has to be released first
+        app.world.resource_mut::<ActionState>().axis = Some(Vec2::X);
+        app.update();
+        assert_eq!(velocity(&app), Vec2::ZERO);
+
+        app.world.resource_mut::<ActionState>().joystick = true;
+        app.update();
+        assert_eq!(velocity(&app), Vec2::new(100.0, 0.0));
+        assert!(!app.world.resource::<PlayerController>().enabled);
+    }
 }
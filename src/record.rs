@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+pub const BEST_SCORE_KEY: &str = "best_score";
+/// The run that survived the longest, whatever its score.
+pub const BEST_TIME_KEY: &str = "best_time";
+// Only meant to make hand-edited values detectable, not to be a secret.
+const RECORD_KEY: &[u8; blake3::KEY_LEN] = b"DodgeFireBall best score key v1!";
+
+/// A finished run worth keeping as the best one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScoreRecord {
+    pub score: u64,
+    pub seed: u64,
+    /// Time survived, as counted by `RunClock`.
+    pub duration: Duration,
+    /// blake3 hash of the run's replay file.
+    pub replay_hash: [u8; blake3::OUT_LEN],
+}
+
+/// A [`ScoreRecord`] together with its blake3 MAC, as it is persisted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignedRecord {
+    record: ScoreRecord,
+    mac: [u8; blake3::OUT_LEN],
+}
+
+impl ScoreRecord {
+    fn mac(&self) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new_keyed(RECORD_KEY);
+        hasher.update(&self.score.to_le_bytes());
+        hasher.update(&self.seed.to_le_bytes());
+        hasher.update(&self.duration.as_nanos().to_le_bytes());
+        hasher.update(&self.replay_hash);
+        hasher.finalize()
+    }
+
+    pub fn sign(self) -> SignedRecord {
+        SignedRecord {
+            mac: *self.mac().as_bytes(),
+            record: self,
+        }
+    }
+}
+
+impl SignedRecord {
+    /// Returns the record only if it was not modified since it was signed.
+    pub fn verify(self) -> Option<ScoreRecord> {
+        // `blake3::Hash` comparisons are constant-time
+        if self.record.mac() == blake3::Hash::from(self.mac) {
+            Some(self.record)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ScoreRecord {
+        ScoreRecord {
+            score: 1200,
+            seed: 7,
+            duration: Duration::from_secs_f32(42.5),
+            replay_hash: [3; blake3::OUT_LEN],
+        }
+    }
+
+    #[test]
+    fn signed_record_verifies() {
+        assert_eq!(record().sign().verify(), Some(record()));
+        // Also once stored and loaded back
+        let stored = ron::to_string(&record().sign()).unwrap();
+        let loaded: SignedRecord = ron::from_str(&stored).unwrap();
+        assert_eq!(loaded.verify(), Some(record()));
+    }
+
+    #[test]
+    fn tampered_record_fails_verification() {
+        let tamperings: [fn(&mut SignedRecord); 5] = [
+            |signed| signed.record.score += 1,
+            |signed| signed.record.seed ^= 1,
+            |signed| signed.record.duration += Duration::from_nanos(1),
+            |signed| signed.record.replay_hash[0] ^= 1,
+            |signed| signed.mac[0] ^= 1,
+        ];
+        for tamper in tamperings {
+            let mut signed = record().sign();
+            tamper(&mut signed);
+            assert_eq!(signed.verify(), None);
+        }
+    }
+
+    #[test]
+    fn garbage_is_not_a_record() {
+        assert!(ron::de::from_bytes::<SignedRecord>(&[0xff, 0x00, 0x13, 0x37]).is_err());
+        // The bare number older versions stored
+        assert!(ron::from_str::<SignedRecord>("1200").is_err());
+        let forged = SignedRecord {
+            record: record(),
+            mac: [0; blake3::OUT_LEN],
+        };
+        assert_eq!(forged.verify(), None);
+    }
+}
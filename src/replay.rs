@@ -0,0 +1,315 @@
+use std::{fmt, fs, io, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, ActionState},
+    config::GameConfig,
+    player::player_controller,
+    rng::GameRng,
+    schedule::InGameSet,
+    state::{GameState, OnRunEnd},
+};
+
+const REPLAY_MAGIC: &[u8; 4] = b"DFBR";
+// Version 1 stored raw touch positions, which can't be turned into the
+// joystick direction, so those replays are rejected
+const REPLAY_VERSION: u8 = 2;
+/// Longest replay accepted, in frames: 4 hours at 60 frames per second. Keeps
+/// a shared file from asking for more memory than any real run needs.
+pub const MAX_REPLAY_FRAMES: usize = 4 * 60 * 60 * 60;
+/// Timestep used for recordings when the config does not force one.
+pub const DEFAULT_TIMESTEP: Duration = Duration::from_nanos(16_666_667);
+// Order of the bits stored in `ReplayFrame::keys`. They were the arrow keys
+// before bindings, which the default bindings of these actions still include.
+const REPLAY_ACTIONS: [Action; 4] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::MoveUp,
+    Action::MoveDown,
+];
+// Bit of `ReplayFrame::keys` set on the frames a dash was asked. Dashes start
+// on a press, so the press is stored rather than the held state.
+const REPLAY_DASH_BIT: u8 = 1 << REPLAY_ACTIONS.len();
+// Bit of `ReplayFrame::keys` set when the axis came from the virtual joystick
+const REPLAY_JOYSTICK_BIT: u8 = REPLAY_DASH_BIT << 1;
+
+/// Inputs `player_controller` consumed during a single InGame frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayFrame {
+    pub keys: u8,
+    /// Analog direction of the left stick or the virtual joystick.
+    pub axis: Option<Vec2>,
+}
+
+/// Everything needed to play a run again: the seed, the timestep and one
+/// entry per InGame frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub timestep: Duration,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    /// Holds more than [`MAX_REPLAY_FRAMES`] frames.
+    TooLong,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    /// Writes each finished run to the given file, replacing the previous one.
+    Record(PathBuf),
+    /// Feeds the replay back instead of the player's inputs.
+    Play(Replay),
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    frames: Vec<ReplayFrame>,
+    /// Replay of the last run that reached GameOver.
+    pub last_run: Option<Replay>,
+}
+
+#[derive(Resource)]
+struct ReplayPlayer {
+    replay: Replay,
+    next_frame: usize,
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .add_systems(
+                Update,
+                (
+                    feed_replay_input.run_if(resource_exists::<ReplayPlayer>),
+                    record_input,
+                )
+                    .chain()
+                    .before(player_controller)
+                    .in_set(InGameSet::UserInput),
+            )
+            .add_systems(OnEnter(GameState::GameOver), finish_recording)
+            .add_systems(OnRunEnd, reset_recording);
+        let replay = app
+            .world
+            .get_resource::<GameConfig>()
+            .map(|config| config.replay.clone());
+        if let Some(ReplayMode::Play(replay)) = replay {
+            app.insert_resource(ReplayPlayer {
+                replay,
+                next_frame: 0,
+            });
+        }
+    }
+}
+
+impl ReplayFrame {
+    fn read(actions: &ActionState) -> Self {
+        let mut keys = 0;
+        for (bit, action) in REPLAY_ACTIONS.iter().enumerate() {
+            if actions.pressed(*action) {
+                keys |= 1 << bit;
+            }
+        }
+        if actions.just_pressed(Action::Dash) {
+            keys |= REPLAY_DASH_BIT;
+        }
+        if actions.joystick {
+            keys |= REPLAY_JOYSTICK_BIT;
+        }
+        Self {
+            keys,
+            axis: actions.axis,
+        }
+    }
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ReplayError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), ReplayError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Consecutive identical frames are stored once with a repeat count.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut runs: Vec<(u32, &ReplayFrame)> = Vec::new();
+        for frame in &self.frames {
+            match runs.last_mut() {
+                Some((count, last)) if *last == frame && *count < u32::MAX => *count += 1,
+                _ => runs.push((1, frame)),
+            }
+        }
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.timestep.as_nanos() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (count, frame) in runs {
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.push(frame.keys);
+            match frame.axis {
+                Some(axis) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&axis.x.to_le_bytes());
+                    bytes.extend_from_slice(&axis.y.to_le_bytes());
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = ByteReader { bytes };
+        if reader.take(REPLAY_MAGIC.len())? != REPLAY_MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let seed = reader.u64()?;
+        let timestep = Duration::from_nanos(reader.u64()?);
+        let mut frames = Vec::new();
+        for _ in 0..reader.u32()? {
+            let count = reader.u32()? as usize;
+            let keys = reader.u8()?;
+            let axis = if reader.u8()? != 0 {
+                Some(Vec2::new(reader.f32()?, reader.f32()?))
+            } else {
+                None
+            };
+            if count > MAX_REPLAY_FRAMES - frames.len() {
+                return Err(ReplayError::TooLong);
+            }
+            let frame = ReplayFrame { keys, axis };
+            frames.resize(frames.len() + count, frame);
+        }
+        Ok(Self {
+            seed,
+            timestep,
+            frames,
+        })
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        if self.bytes.len() < len {
+            return Err(ReplayError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ReplayError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "replay i/o error: {error}"),
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay version {version}")
+            }
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+            ReplayError::TooLong => write!(f, "replay is longer than {MAX_REPLAY_FRAMES} frames"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// Overwrites the live inputs with the recorded ones, so the same systems
+// consume them exactly as they did during the recording.
+fn feed_replay_input(mut player: ResMut<ReplayPlayer>, mut actions: ResMut<ActionState>) {
+    let frame = player
+        .replay
+        .frames
+        .get(player.next_frame)
+        .cloned()
+        .unwrap_or_default();
+    player.next_frame += 1;
+    for (bit, action) in REPLAY_ACTIONS.iter().enumerate() {
+        if frame.keys & (1 << bit) != 0 {
+            actions.press(*action);
+        } else {
+            actions.release(*action);
+        }
+    }
+    // Only the recorded presses dash, whatever the live input does
+    actions.reset(Action::Dash);
+    if frame.keys & REPLAY_DASH_BIT != 0 {
+        actions.press(Action::Dash);
+    }
+    actions.axis = frame.axis;
+    actions.joystick = frame.keys & REPLAY_JOYSTICK_BIT != 0;
+}
+
+fn record_input(mut recorder: ResMut<ReplayRecorder>, actions: Res<ActionState>) {
+    let frame = ReplayFrame::read(&actions);
+    recorder.frames.push(frame);
+}
+
+pub fn finish_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    rng: Res<GameRng>,
+    config: Res<GameConfig>,
+) {
+    let replay = Replay {
+        seed: rng.seed(),
+        timestep: config.fixed_delta.unwrap_or(DEFAULT_TIMESTEP),
+        frames: std::mem::take(&mut recorder.frames),
+    };
+    if let ReplayMode::Record(path) = &config.replay {
+        if let Err(error) = replay.save(path) {
+            warn!("failed to save replay to {}: {error}", path.display());
+        }
+    }
+    recorder.last_run = Some(replay);
+}
+
+fn reset_recording(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.frames.clear();
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{config::GameConfig, state::OnRunEnd};
+
+/// Every random decision of a run draws from here, so a run is fully
+/// determined by its seed and the player's inputs.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app
+            .world
+            .get_resource::<GameConfig>()
+            .and_then(|config| config.seed)
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        app.insert_resource(GameRng::new(seed))
+            .add_systems(OnRunEnd, next_run_seed);
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Seed the current run started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Each new run gets its own seed, derived from the previous one, so a single
+// seed still reproduces a whole session.
+fn next_run_seed(mut rng: ResMut<GameRng>) {
+    let seed = rng.gen();
+    rng.reseed(seed);
+}
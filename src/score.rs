@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    collision::{FIREBALL_GROUP, PLAYER_GROUP},
+    fireball::Fireball,
+    lifetime::FadeOut,
+    player::Player,
+    schedule::InGameSet,
+    state::OnRunEnd,
+    tuning::GameTuning,
+};
+
+// Points for each second survived and for each near-miss, before the multiplier
+const SURVIVAL_POINTS: f32 = 10.0;
+const NEAR_MISS_POINTS: f32 = 100.0;
+const MAX_MULTIPLIER: u32 = 8;
+
+/// Score of the current run: points for the time survived and for the
+/// fireballs dodged narrowly, times a combo multiplier.
+#[derive(Resource)]
+pub struct Score {
+    pub points: u64,
+    /// Grows with each near-miss, and drops back by one every
+    /// `combo_decay_time` seconds without any.
+    pub multiplier: u32,
+    pub best_multiplier: u32,
+    pub near_misses: u32,
+    /// Points earned but not yet added to `points`.
+    fraction: f32,
+    combo_timer: Timer,
+}
+
+/// Fireball within `near_miss_distance` of the player. It counts as a
+/// near-miss if it gets away without ever touching the player.
+#[derive(Component)]
+struct Grazing {
+    touched: bool,
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .add_systems(
+                Update,
+                (add_survival_points, detect_near_misses, decay_combo)
+                    .chain()
+                    .in_set(InGameSet::EntityUpdates),
+            )
+            .add_systems(OnRunEnd, reset_score);
+    }
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            points: 0,
+            multiplier: 1,
+            best_multiplier: 1,
+            near_misses: 0,
+            fraction: 0.0,
+            combo_timer: Timer::default(),
+        }
+    }
+}
+
+impl Score {
+    /// Adds `points` times the multiplier.
+    pub fn add(&mut self, points: f32) {
+        self.fraction += points * self.multiplier as f32;
+        let whole = self.fraction.floor();
+        self.points += whole as u64;
+        self.fraction -= whole;
+    }
+
+    fn near_miss(&mut self, combo_decay_time: f32) {
+        self.near_misses += 1;
+        self.add(NEAR_MISS_POINTS);
+        self.multiplier = (self.multiplier + 1).min(MAX_MULTIPLIER);
+        self.best_multiplier = self.best_multiplier.max(self.multiplier);
+        self.combo_timer = Timer::from_seconds(combo_decay_time, TimerMode::Repeating);
+    }
+}
+
+fn add_survival_points(mut score: ResMut<Score>, time: Res<Time>) {
+    score.add(SURVIVAL_POINTS * time.delta_seconds());
+}
+
+/// Tracks the fireballs around the player with two rapier shape queries: one
+/// with the player collider grown by `near_miss_distance`, one with the
+/// collider itself to tell a near-miss from a contact.
+fn detect_near_misses(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    player_query: Query<(&Transform, &Collider), With<Player>>,
+    mut grazing_query: Query<(Entity, &mut Grazing)>,
+    fireball_query: Query<(), (With<Fireball>, Without<FadeOut>)>,
+    mut score: ResMut<Score>,
+    tuning: Res<GameTuning>,
+) {
+    let Ok((transform, collider)) = player_query.get_single() else {
+        return;
+    };
+    let Some(cuboid) = collider.as_cuboid() else {
+        return;
+    };
+    let half_extents = cuboid.half_extents();
+    let position = transform.translation.xy();
+    let rotation = transform.rotation.to_euler(EulerRot::XYZ).2;
+    // Hazards go through an invulnerable player, so the groups of the
+    // player collider can't be used here
+    let filter = QueryFilter::new().groups(CollisionGroups::new(PLAYER_GROUP, FIREBALL_GROUP));
+    let mut near = Vec::new();
+    rapier_context.intersections_with_shape(
+        position,
+        rotation,
+        &Collider::round_cuboid(half_extents.x, half_extents.y, tuning.near_miss_distance),
+        filter,
+        |entity| {
+            if fireball_query.contains(entity) {
+                near.push(entity);
+            }
+            true
+        },
+    );
+    let mut touching = Vec::new();
+    rapier_context.intersections_with_shape(position, rotation, collider, filter, |entity| {
+        touching.push(entity);
+        true
+    });
+
+    for &entity in &near {
+        let touched = touching.contains(&entity);
+        match grazing_query.get_mut(entity) {
+            Ok((_, mut grazing)) => grazing.touched |= touched,
+            Err(_) => {
+                commands.entity(entity).insert(Grazing { touched });
+            }
+        }
+    }
+    for (entity, grazing) in grazing_query.iter() {
+        if near.contains(&entity) {
+            continue;
+        }
+        commands.entity(entity).remove::<Grazing>();
+        if !grazing.touched {
+            score.near_miss(tuning.combo_decay_time);
+        }
+    }
+}
+
+fn decay_combo(mut score: ResMut<Score>, time: Res<Time>) {
+    if score.multiplier == 1 {
+        return;
+    }
+    score.combo_timer.tick(time.delta());
+    let drops = score.combo_timer.times_finished_this_tick();
+    score.multiplier = score.multiplier.saturating_sub(drops).max(1);
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const DECAY_TIME: f32 = 3.0;
+
+    fn combo_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Score>()
+            .add_systems(Update, decay_combo);
+        app
+    }
+
+    fn wait(app: &mut App, seconds: f32) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    fn multiplier(app: &App) -> u32 {
+        app.world.resource::<Score>().multiplier
+    }
+
+    #[test]
+    fn combo_drops_one_step_per_decay_time() {
+        let mut app = combo_app();
+        for _ in 0..3 {
+            app.world.resource_mut::<Score>().near_miss(DECAY_TIME);
+        }
+        assert_eq!(multiplier(&app), 4);
+
+        wait(&mut app, 2.9);
+        assert_eq!(multiplier(&app), 4);
+        wait(&mut app, 0.2);
+        assert_eq!(multiplier(&app), 3);
+        // A near-miss starts the wait over
+        wait(&mut app, 2.0);
+        app.world.resource_mut::<Score>().near_miss(DECAY_TIME);
+        assert_eq!(multiplier(&app), 4);
+        wait(&mut app, 2.0);
+        assert_eq!(multiplier(&app), 4);
+        // Long breaks drop several steps at once, but never below 1
+        wait(&mut app, 7.0);
+        assert_eq!(multiplier(&app), 1);
+        wait(&mut app, 30.0);
+        assert_eq!(multiplier(&app), 1);
+        assert_eq!(app.world.resource::<Score>().best_multiplier, 4);
+    }
+
+    #[test]
+    fn near_misses_pay_more_with_the_combo() {
+        let mut score = Score::default();
+        score.near_miss(DECAY_TIME);
+        assert_eq!(score.points, 100);
+        score.near_miss(DECAY_TIME);
+        assert_eq!(score.points, 300);
+        for _ in 0..20 {
+            score.near_miss(DECAY_TIME);
+        }
+        assert_eq!(score.multiplier, MAX_MULTIPLIER);
+        // Fractions of points add up
+        let points = score.points;
+        for _ in 0..4 {
+            score.add(0.05);
+        }
+        assert_eq!(score.points, points + 1);
+    }
+}
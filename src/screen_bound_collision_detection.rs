@@ -1,6 +1,25 @@
-use bevy::{prelude::*, window::PrimaryWindow};
-use bevy_rapier2d::dynamics::Velocity;
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+use bevy_rapier2d::{pipeline::CollisionEvent, plugin::PhysicsSet, prelude::*};
 
+use crate::{
+    collision::wall_groups,
+    schedule::{run_physics_schedule, PhysicsSchedule},
+    state::GameState,
+};
+
+// Thick enough that the fastest fireballs can't tunnel through in one step
+const WALL_THICKNESS: f32 = 200.0;
+// Room left between a wall and a body moved back inside the window
+const BODY_MARGIN: f32 = 64.0;
+
+/// Static collider bounding the arena along one edge of the window.
+#[derive(Component)]
+pub struct Wall;
+
+/// Sent when an entity bounces off a [`Wall`].
 #[derive(Event)]
 pub struct ScreenCollisionEvent {
     pub entity: Entity,
@@ -10,46 +29,107 @@ pub struct ScreenCollisionDetectionPlugin;
 
 impl Plugin for ScreenCollisionDetectionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ScreenCollisionEvent>();
+        app.add_event::<ScreenCollisionEvent>()
+            .add_systems(Startup, spawn_walls)
+            .add_systems(
+                PreUpdate,
+                (spawn_walls, keep_bodies_inside)
+                    .chain()
+                    .before(run_physics_schedule)
+                    .run_if(on_event::<WindowResized>()),
+            )
+            .add_systems(
+                PhysicsSchedule,
+                detect_wall_collisions
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(GameState::InGame)),
+            );
     }
 }
 
-pub fn handle_screen_bound_collisions<T: Component>(
+/// Replaces the walls with new ones fitting the primary window.
+fn spawn_walls(
+    mut commands: Commands,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    mut component_query: Query<(Entity, &Transform, &mut Velocity), With<T>>,
+    wall_query: Query<Entity, With<Wall>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    for entity in wall_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let half_width = window.width() / 2.0;
+    let half_height = window.height() / 2.0;
+    let offset = WALL_THICKNESS / 2.0;
+    // Vertical walls overlap the horizontal ones so the corners are closed
+    let walls = [
+        (
+            Vec2::new(-half_width - offset, 0.0),
+            Vec2::new(offset, half_height + WALL_THICKNESS),
+        ),
+        (
+            Vec2::new(half_width + offset, 0.0),
+            Vec2::new(offset, half_height + WALL_THICKNESS),
+        ),
+        (
+            Vec2::new(0.0, -half_height - offset),
+            Vec2::new(half_width, offset),
+        ),
+        (
+            Vec2::new(0.0, half_height + offset),
+            Vec2::new(half_width, offset),
+        ),
+    ];
+    for (center, half_extents) in walls {
+        commands.spawn((
+            TransformBundle::from_transform(Transform::from_translation(center.extend(0.0))),
+            Wall,
+            RigidBody::Fixed,
+            Collider::cuboid(half_extents.x, half_extents.y),
+            wall_groups(),
+            // Bounces stay perfectly elastic whatever the other body uses
+            Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+        ));
+    }
+}
+
+// A shrinking window can leave bodies outside of the new walls
+fn keep_bodies_inside(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<&mut Transform, (With<Velocity>, Without<Wall>)>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let half_size =
+        (Vec2::new(window.width(), window.height()) / 2.0 - BODY_MARGIN).max(Vec2::ZERO);
+    for mut transform in query.iter_mut() {
+        let translation = transform.translation.xy().clamp(-half_size, half_size);
+        transform.translation = translation.extend(transform.translation.z);
+    }
+}
+
+fn detect_wall_collisions(
+    mut collision_event_reader: EventReader<CollisionEvent>,
     mut collision_event_writer: EventWriter<ScreenCollisionEvent>,
+    wall_query: Query<(), With<Wall>>,
 ) {
-    let window = window_query.get_single().unwrap();
-    let height = window.height() / 2.0;
-    let width = window.width() / 2.0;
-    for (entity, transform, mut velocity) in &mut component_query {
-        let point_east = (
-            transform.translation.x + 2.0 * transform.scale.x + 2.0 * transform.scale.x,
-            transform.translation.y,
-        );
-        let point_south = (
-            transform.translation.x,
-            transform.translation.y - 2.0 * transform.scale.y - 2.0 * transform.scale.y,
-        );
-        let point_west = (
-            transform.translation.x - 2.0 * transform.scale.x - 2.0 * transform.scale.x,
-            transform.translation.y,
-        );
-        let point_north = (
-            transform.translation.x,
-            transform.translation.y + 2.0 * transform.scale.y + 2.0 * transform.scale.y,
-        );
-        let mut send_event = false;
-        if point_east.0 >= width || point_west.0 <= -width {
-            (*velocity).linvel.x *= -1.0;
-            send_event = true;
-        }
-        if point_south.1 >= height || point_north.1 < -height {
-            (*velocity).linvel.y *= -1.0;
-            send_event = true;
-        }
-        if send_event {
-            collision_event_writer.send(ScreenCollisionEvent { entity });
+    for collision in collision_event_reader.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision else {
+            continue;
+        };
+        if wall_query.contains(*e1) {
+            collision_event_writer.send(ScreenCollisionEvent { entity: *e2 });
+        } else if wall_query.contains(*e2) {
+            collision_event_writer.send(ScreenCollisionEvent { entity: *e1 });
         }
     }
 }
@@ -1,18 +1,37 @@
-use bevy::prelude::*;
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
 
-use crate::explosion::ExplosionEndedEvent;
+use crate::{
+    actions::{Action, ActionState},
+    explosion::ExplosionEndedEvent,
+};
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
     #[default]
+    MainMenu,
+    Settings,
+    Controls,
+    Leaderboard,
     InGame,
     Paused,
     GameOver,
 }
 
+/// Runs when a new run begins, either from the main menu or after a GameOver,
+/// but not when resuming from a pause.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OnRunStart;
+
+/// Runs when a run is left for good: after its GameOver, or when it is
+/// restarted or quit from the pause menu.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OnRunEnd;
+
 #[derive(Resource, Default)]
 pub struct StateFlags {
     pub explosion_ended: bool,
+    /// Makes the next Paused -> InGame transition start a new run instead of resuming.
+    pub restart_requested: bool,
 }
 
 pub struct StatePlugin;
@@ -21,7 +40,42 @@ impl Plugin for StatePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<StateFlags>()
             .insert_state(GameState::default())
+            .init_schedule(OnRunStart)
+            .init_schedule(OnRunEnd)
             .add_systems(OnEnter(GameState::InGame), restart_time)
+            .add_systems(OnEnter(GameState::Paused), pause_time)
+            .add_systems(
+                OnExit(GameState::GameOver),
+                (clear_explosion_ended, run_on_run_end_schedule),
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::Paused,
+                    to: GameState::MainMenu,
+                },
+                run_on_run_end_schedule,
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::Paused,
+                    to: GameState::InGame,
+                },
+                restart_run,
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::MainMenu,
+                    to: GameState::InGame,
+                },
+                run_on_run_start_schedule,
+            )
+            .add_systems(
+                OnTransition {
+                    from: GameState::GameOver,
+                    to: GameState::InGame,
+                },
+                run_on_run_start_schedule,
+            )
             .add_systems(
                 Update,
                 (check_explosion_ended, game_state_input_events).chain(),
@@ -32,33 +86,63 @@ impl Plugin for StatePlugin {
 pub fn game_state_input_events(
     mut next_state: ResMut<NextState<GameState>>,
     state: Res<State<GameState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut state_flags: ResMut<StateFlags>,
+    actions: Res<ActionState>,
+    state_flags: Res<StateFlags>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(Action::Pause) {
         match state.get() {
+            GameState::MainMenu => {}
+            GameState::Settings => next_state.set(GameState::MainMenu),
+            GameState::Controls => next_state.set(GameState::Settings),
+            GameState::Leaderboard => next_state.set(GameState::MainMenu),
             GameState::InGame => next_state.set(GameState::Paused),
             GameState::Paused => next_state.set(GameState::InGame),
             GameState::GameOver => {
                 if state_flags.explosion_ended {
                     next_state.set(GameState::InGame);
-                    state_flags.explosion_ended = false;
                 }
             }
         }
     }
 }
 
+fn run_on_run_start_schedule(world: &mut World) {
+    world.run_schedule(OnRunStart);
+}
+
+fn run_on_run_end_schedule(world: &mut World) {
+    world.run_schedule(OnRunEnd);
+}
+
+fn restart_run(world: &mut World) {
+    let mut state_flags = world.resource_mut::<StateFlags>();
+    if state_flags.restart_requested {
+        state_flags.restart_requested = false;
+        world.run_schedule(OnRunEnd);
+        world.run_schedule(OnRunStart);
+    }
+}
+
 fn restart_time(mut time: ResMut<Time<Virtual>>) {
     time.unpause()
 }
 
+// Freezes everything driven by virtual time: the fixed fireball spawner,
+// physics and the animation timers.
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause()
+}
+
+fn clear_explosion_ended(mut state_flags: ResMut<StateFlags>) {
+    state_flags.explosion_ended = false;
+}
+
 fn check_explosion_ended(
     mut explosion_event_reader: EventReader<ExplosionEndedEvent>,
     mut state_flags: ResMut<StateFlags>,
 ) {
-    for _ in explosion_event_reader.read() {
+    if !explosion_event_reader.is_empty() {
+        explosion_event_reader.clear();
         state_flags.explosion_ended = true;
-        break;
     }
 }
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+
+use crate::{fireball::Fireball, schedule::InGameSet, state::OnRunEnd};
+
+/// Figures of the current run, shown on the GameOver panel.
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub max_fireball_speed: f32,
+    pub dashes: u32,
+    pub new_record: bool,
+    pub new_best_time: bool,
+}
+
+/// Time survived in the current run. It only runs InGame, so it stands still
+/// while paused and during the GameOver explosion.
+#[derive(Resource, Default)]
+pub struct RunClock {
+    elapsed: Duration,
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .init_resource::<RunClock>()
+            .add_systems(
+                Update,
+                (tick_run_clock, track_max_fireball_speed).in_set(InGameSet::EntityUpdates),
+            )
+            .add_systems(OnRunEnd, (reset_stats, reset_run_clock));
+    }
+}
+
+/// Formats a run time as `mm:ss.cs`.
+pub fn format_run_time(time: Duration) -> String {
+    let centiseconds = time.as_millis() / 10;
+    format!(
+        "{:02}:{:02}.{:02}",
+        centiseconds / 6000,
+        centiseconds / 100 % 60,
+        centiseconds % 100
+    )
+}
+
+impl RunClock {
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+fn tick_run_clock(time: Res<Time>, mut clock: ResMut<RunClock>) {
+    clock.elapsed += time.delta();
+}
+
+fn track_max_fireball_speed(
+    query: Query<&Velocity, With<Fireball>>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    for velocity in query.iter() {
+        run_stats.max_fireball_speed = run_stats.max_fireball_speed.max(velocity.linvel.length());
+    }
+}
+
+fn reset_stats(mut run_stats: ResMut<RunStats>) {
+    *run_stats = RunStats::default();
+}
+
+fn reset_run_clock(mut clock: ResMut<RunClock>) {
+    *clock = RunClock::default();
+}
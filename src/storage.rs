@@ -0,0 +1,165 @@
+use std::{collections::HashMap, fmt, fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+#[cfg(not(target_os = "android"))]
+use bevy_pkv::{GetError, PkvStore};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Key-value backend the persisted scores are written to. Values are RON
+/// strings, so every backend stores the same format.
+pub trait ScoreStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError>;
+    fn set(&mut self, key: &str, value: String) -> Result<(), StoreError>;
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Backend(String),
+    Format(String),
+}
+
+/// Typed access to the [`ScoreStore`] the game was configured with.
+#[derive(Resource)]
+pub struct ScoreStorage {
+    store: Box<dyn ScoreStore>,
+}
+
+/// Keeps one `<key>.ron` file per value inside a directory.
+pub struct FileScoreStore {
+    dir: PathBuf,
+}
+
+/// Keeps the values in memory only, so nothing outlives the app. Used by
+/// headless apps and when the platform has nowhere to save.
+#[derive(Default)]
+pub struct MemoryScoreStore {
+    values: HashMap<String, String>,
+}
+
+#[cfg(not(target_os = "android"))]
+pub struct PkvScoreStore(PkvStore);
+
+impl ScoreStorage {
+    pub fn new(store: impl ScoreStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+        }
+    }
+
+    /// Default store of the platform: `PkvStore` on desktop, a file store in
+    /// the app data directory on Android.
+    #[cfg(not(target_os = "android"))]
+    pub fn platform_default() -> Self {
+        Self::new(PkvScoreStore(PkvStore::new("Simomaster1", "DodgeFireBall")))
+    }
+
+    #[cfg(target_os = "android")]
+    pub fn platform_default() -> Self {
+        match bevy::winit::ANDROID_APP
+            .get()
+            .and_then(|android_app| android_app.internal_data_path())
+        {
+            Some(dir) => Self::new(FileScoreStore::new(dir)),
+            None => {
+                warn!("failed to find the app data directory, records won't be saved");
+                Self::new(MemoryScoreStore::default())
+            }
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, StoreError> {
+        match self.store.get(key)? {
+            Some(value) => ron::from_str(&value)
+                .map(Some)
+                .map_err(|error| StoreError::Format(error.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) -> Result<(), StoreError> {
+        let value = ron::to_string(value).map_err(|error| StoreError::Format(error.to_string()))?;
+        self.store.set(key, value)
+    }
+}
+
+impl FileScoreStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.ron"))
+    }
+}
+
+impl ScoreStore for FileScoreStore {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        match fs::read_to_string(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: String) -> Result<(), StoreError> {
+        fs::create_dir_all(&self.dir)?;
+        // Write aside and rename, so a crash never leaves a half-written value
+        let path = self.path(key);
+        let tmp_path = path.with_extension("ron.tmp");
+        fs::write(&tmp_path, value)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+impl ScoreStore for MemoryScoreStore {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, value: String) -> Result<(), StoreError> {
+        self.values.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl ScoreStore for PkvScoreStore {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        match self.0.get::<String>(key) {
+            Ok(value) => Ok(Some(value)),
+            Err(GetError::NotFound) => Ok(None),
+            // Older versions stored the best score as a bare number, which
+            // reads back as the same RON
+            Err(error) => match self.0.get::<u64>(key) {
+                Ok(value) => Ok(Some(value.to_string())),
+                Err(_) => Err(StoreError::Backend(error.to_string())),
+            },
+        }
+    }
+
+    fn set(&mut self, key: &str, value: String) -> Result<(), StoreError> {
+        self.0
+            .set_string(key, &value)
+            .map_err(|error| StoreError::Backend(error.to_string()))
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(error) => write!(f, "score store i/o error: {error}"),
+            StoreError::Backend(error) => write!(f, "score store error: {error}"),
+            StoreError::Format(error) => write!(f, "malformed stored value: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
@@ -0,0 +1,391 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::archetype::{ArchetypeTuning, FireballArchetype};
+
+const TUNING_PATH: &str = "game.tuning.ron";
+
+/// Balance values of the game. Loaded from `assets/game.tuning.ron`, any
+/// missing field keeps its default value.
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GameTuning {
+    pub player_scale: f32,
+    /// Hits the player can take in a run, 1 ends the run on the first hit.
+    pub player_lives: u32,
+    /// Seconds the player can't be hit again after losing a life.
+    pub invulnerability_time: f32,
+    /// Speed the player is pushed away from a fireball at when hit.
+    pub knockback_speed: f32,
+    /// Speed of the player, in pixels per second.
+    pub initial_velocity: f32,
+    /// Pixels per second² the analog movement speeds up at, `None` for instant turns.
+    pub player_acceleration: Option<f32>,
+    /// Pixels per second² the analog movement slows down at, `None` for instant.
+    pub player_deceleration: Option<f32>,
+    /// Speed of the player during a dash, in pixels per second.
+    pub dash_speed: f32,
+    /// Seconds a dash lasts, during which hazards go through the player.
+    pub dash_duration: f32,
+    /// Seconds from the start of a dash until the next one is possible.
+    pub dash_cooldown: f32,
+    /// Gamepad stick positions closer to the center than this are ignored, from 0 to 1.
+    pub gamepad_dead_zone: f32,
+    /// Seconds between two fireball spawns.
+    pub fireball_spawn_time: f64,
+    /// Seconds a spawn warning is shown before its fireball appears.
+    pub spawn_warning_time: f32,
+    /// Fireballs never appear closer than this to the player, in pixels.
+    pub min_spawn_distance: f32,
+    pub speed_multiplier: f32,
+    pub initial_fireball_speed: f32,
+    /// Radius of the fireball collider, before `fireball_scale` is applied.
+    pub fireball_radius: f32,
+    pub fireball_scale: f32,
+    /// Seconds a fireball lives, `None` to keep it until the run ends.
+    pub fireball_lifetime: Option<f32>,
+    /// Wall bounces a fireball survives, `None` for no limit.
+    pub fireball_max_bounces: Option<u32>,
+    /// Seconds an expired fireball takes to fade out.
+    pub fireball_fade_time: f32,
+    /// Radians per second a homing fireball turns toward the player at.
+    pub homing_turn_rate: f32,
+    /// Wall bounces a splitter fireball makes before it splits.
+    pub splitter_bounces: u32,
+    /// Fireball archetypes the spawner draws from, each listed once.
+    pub archetypes: Vec<ArchetypeTuning>,
+    /// Seconds between two pickup spawns.
+    pub pickup_spawn_time: f32,
+    /// Seconds a pickup waits to be collected before it disappears.
+    pub pickup_lifetime: f32,
+    /// Seconds the slow-motion pickup lasts.
+    pub slow_motion_time: f32,
+    /// Speed of the fireballs during a slow motion, relative to their usual speed.
+    pub slow_motion_factor: f32,
+    /// Seconds the shrink pickup lasts.
+    pub shrink_time: f32,
+    /// Size of the shrunk player, relative to its usual size.
+    pub shrink_factor: f32,
+    /// Fireballs closer than this to the player are destroyed by a bomb, in pixels.
+    pub bomb_radius: f32,
+    /// A fireball passing closer than this to the player without touching it
+    /// is a near-miss, in pixels.
+    pub near_miss_distance: f32,
+    /// Seconds without a near-miss before the score multiplier drops by one.
+    pub combo_decay_time: f32,
+    pub background_scale: f32,
+}
+
+#[derive(Resource)]
+struct GameTuningHandle(Handle<GameTuning>);
+
+#[derive(Default)]
+struct GameTuningLoader;
+
+#[derive(Debug)]
+pub enum GameTuningLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+/// Keeps [`GameTuning`] up to date with the tuning file. In debug builds the
+/// file is watched, so edits apply while the game runs, unless the default
+/// `dev` feature is turned off.
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GameTuning>()
+            .register_asset_loader(GameTuningLoader)
+            .add_systems(Startup, load_tuning)
+            .add_systems(PreUpdate, apply_tuning);
+    }
+}
+
+impl Default for GameTuning {
+    fn default() -> Self {
+        Self {
+            player_scale: 1.0,
+            player_lives: 1,
+            invulnerability_time: 2.0,
+            knockback_speed: 250.0,
+            initial_velocity: 100.0,
+            player_acceleration: None,
+            player_deceleration: None,
+            dash_speed: 450.0,
+            dash_duration: 0.25,
+            dash_cooldown: 2.0,
+            gamepad_dead_zone: 0.2,
+            fireball_spawn_time: 10.0,
+            spawn_warning_time: 1.0,
+            min_spawn_distance: 200.0,
+            speed_multiplier: 1.75,
+            initial_fireball_speed: 40.0,
+            fireball_radius: 512.0,
+            fireball_scale: 0.049,
+            fireball_lifetime: None,
+            fireball_max_bounces: None,
+            fireball_fade_time: 0.5,
+            homing_turn_rate: 1.0,
+            splitter_bounces: 2,
+            archetypes: ArchetypeTuning::defaults(),
+            pickup_spawn_time: 12.0,
+            pickup_lifetime: 8.0,
+            slow_motion_time: 5.0,
+            slow_motion_factor: 0.5,
+            shrink_time: 8.0,
+            shrink_factor: 0.5,
+            bomb_radius: 250.0,
+            near_miss_distance: 24.0,
+            combo_decay_time: 3.0,
+            background_scale: 3.1,
+        }
+    }
+}
+
+impl GameTuning {
+    /// Balance values of `archetype`, the defaults ones if it isn't listed.
+    pub fn archetype(&self, archetype: FireballArchetype) -> ArchetypeTuning {
+        self.archetypes
+            .iter()
+            .chain(&ArchetypeTuning::defaults())
+            .find(|tuning| tuning.archetype == archetype)
+            .copied()
+            .expect("the default tuning lists every archetype")
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("player_scale", self.player_scale),
+            ("invulnerability_time", self.invulnerability_time),
+            ("knockback_speed", self.knockback_speed),
+            ("initial_velocity", self.initial_velocity),
+            (
+                "player_acceleration",
+                self.player_acceleration.unwrap_or(1.0),
+            ),
+            (
+                "player_deceleration",
+                self.player_deceleration.unwrap_or(1.0),
+            ),
+            ("dash_speed", self.dash_speed),
+            ("dash_duration", self.dash_duration),
+            ("dash_cooldown", self.dash_cooldown),
+            ("fireball_spawn_time", self.fireball_spawn_time as f32),
+            ("min_spawn_distance", self.min_spawn_distance),
+            ("speed_multiplier", self.speed_multiplier),
+            ("initial_fireball_speed", self.initial_fireball_speed),
+            ("fireball_radius", self.fireball_radius),
+            ("fireball_scale", self.fireball_scale),
+            ("fireball_lifetime", self.fireball_lifetime.unwrap_or(1.0)),
+            ("fireball_fade_time", self.fireball_fade_time),
+            ("homing_turn_rate", self.homing_turn_rate),
+            ("pickup_spawn_time", self.pickup_spawn_time),
+            ("pickup_lifetime", self.pickup_lifetime),
+            ("slow_motion_time", self.slow_motion_time),
+            ("slow_motion_factor", self.slow_motion_factor),
+            ("shrink_time", self.shrink_time),
+            ("shrink_factor", self.shrink_factor),
+            ("bomb_radius", self.bomb_radius),
+            ("near_miss_distance", self.near_miss_distance),
+            ("combo_decay_time", self.combo_decay_time),
+            ("background_scale", self.background_scale),
+        ];
+        for (name, value) in values {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("`{name}` must be a positive number, got {value}"));
+            }
+        }
+        if !(0.0..1.0).contains(&self.gamepad_dead_zone) {
+            return Err(format!(
+                "`gamepad_dead_zone` must be between 0 and 1, got {}",
+                self.gamepad_dead_zone
+            ));
+        }
+        for (name, value) in [
+            ("slow_motion_factor", self.slow_motion_factor),
+            ("shrink_factor", self.shrink_factor),
+        ] {
+            if value > 1.0 {
+                return Err(format!("`{name}` must be at most 1.0, got {value}"));
+            }
+        }
+        if self.player_lives == 0 {
+            return Err("`player_lives` must be at least 1".to_string());
+        }
+        if !self.spawn_warning_time.is_finite() || self.spawn_warning_time < 0.0 {
+            return Err(format!(
+                "`spawn_warning_time` must be zero or more, got {}",
+                self.spawn_warning_time
+            ));
+        }
+        if self.splitter_bounces == 0 {
+            return Err("`splitter_bounces` must be at least 1".to_string());
+        }
+        self.validate_archetypes()?;
+        if self.speed_multiplier < 1.0 {
+            return Err(format!(
+                "`speed_multiplier` must be at least 1.0, got {}",
+                self.speed_multiplier
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_archetypes(&self) -> Result<(), String> {
+        for archetype in FireballArchetype::ALL {
+            let count = self
+                .archetypes
+                .iter()
+                .filter(|tuning| tuning.archetype == archetype)
+                .count();
+            if count != 1 {
+                return Err(format!(
+                    "`archetypes` must list {archetype:?} once, got it {count} times"
+                ));
+            }
+        }
+        for tuning in &self.archetypes {
+            let archetype = tuning.archetype;
+            for (name, value) in [
+                ("speed_scale", tuning.speed_scale),
+                ("size_scale", tuning.size_scale),
+            ] {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(format!(
+                        "`{name}` of {archetype:?} must be a positive number, got {value}"
+                    ));
+                }
+            }
+            for (name, value) in [
+                ("base_weight", tuning.base_weight),
+                ("weight_per_step", tuning.weight_per_step),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(format!(
+                        "`{name}` of {archetype:?} must be zero or more, got {value}"
+                    ));
+                }
+            }
+        }
+        if self
+            .archetypes
+            .iter()
+            .all(|tuning| tuning.base_weight == 0.0)
+        {
+            return Err("`archetypes` need a positive `base_weight` to start with".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl AssetLoader for GameTuningLoader {
+    type Asset = GameTuning;
+    type Settings = ();
+    type Error = GameTuningLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<GameTuning, GameTuningLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+impl From<std::io::Error> for GameTuningLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for GameTuningLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Ron(error)
+    }
+}
+
+impl fmt::Display for GameTuningLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameTuningLoaderError::Io(error) => write!(f, "failed to read tuning file: {error}"),
+            GameTuningLoaderError::Ron(error) => write!(f, "malformed tuning file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GameTuningLoaderError {}
+
+fn load_tuning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GameTuningHandle(asset_server.load(TUNING_PATH)));
+}
+
+fn apply_tuning(
+    mut asset_events: EventReader<AssetEvent<GameTuning>>,
+    tuning_assets: Res<Assets<GameTuning>>,
+    handle: Option<Res<GameTuningHandle>>,
+    mut tuning: ResMut<GameTuning>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in asset_events.read() {
+        if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+            continue;
+        }
+        let Some(new_tuning) = tuning_assets.get(&handle.0) else {
+            continue;
+        };
+        match new_tuning.validate() {
+            Ok(()) => {
+                if *tuning != *new_tuning {
+                    info!("applying game tuning from {TUNING_PATH}");
+                    *tuning = new_tuning.clone();
+                }
+            }
+            Err(error) => warn!("ignoring invalid {TUNING_PATH}: {error}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_tuning_matches_the_defaults() {
+        let tuning: GameTuning = ron::from_str(include_str!("../assets/game.tuning.ron")).unwrap();
+        assert_eq!(tuning.validate(), Ok(()));
+        assert_eq!(tuning, GameTuning::default());
+    }
+
+    #[test]
+    fn every_archetype_must_be_listed_once() {
+        let mut tuning = GameTuning::default();
+        tuning.archetypes.pop();
+        assert!(tuning.validate().is_err());
+
+        let mut tuning = GameTuning::default();
+        tuning.archetypes.push(tuning.archetypes[0]);
+        assert!(tuning.validate().is_err());
+
+        let mut tuning = GameTuning::default();
+        tuning.archetypes[0].base_weight = 0.0;
+        assert!(tuning.validate().is_err());
+    }
+}
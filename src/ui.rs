@@ -1,15 +1,30 @@
-use crate::{fireball::INITIAL_FIREBALL_SPEED, state::GameState};
+use crate::{
+    config::GameConfig,
+    pickup::PowerUps,
+    record::{ScoreRecord, SignedRecord, BEST_SCORE_KEY, BEST_TIME_KEY},
+    replay::{finish_recording, ReplayRecorder},
+    score::Score,
+    state::{GameState, OnRunEnd},
+    stats::{format_run_time, RunClock, RunStats},
+    storage::{FileScoreStore, MemoryScoreStore, ScoreStorage},
+    tuning::GameTuning,
+};
 use bevy::{prelude::*, window::PrimaryWindow};
-#[cfg(not(target_os = "android"))]
-use bevy_pkv::PkvStore;
+use std::time::Duration;
 
 const FONT_SIZE: f32 = 60.0;
 
 #[derive(Resource)]
 pub struct GameData {
+    /// Best [`Score`] so far.
     pub record: u64,
+    /// Longest time survived so far.
+    pub best_time: Duration,
+    /// Fireballs spawned in the current run.
     pub n_balls: u64,
     pub current_fireballs_speed: f32,
+    /// Hits the player can still take in the current run.
+    pub lives: u32,
 }
 
 #[derive(Component)]
@@ -22,10 +37,22 @@ impl Plugin for UiPlugin {
         app.init_resource::<GameData>()
             .add_systems(Startup, spawn_ui)
             .add_systems(Update, update_ui)
-            .add_systems(OnExit(GameState::GameOver), reset_score);
-        #[cfg(not(target_os = "android"))]
-        app.insert_resource(PkvStore::new("Simomaster1", "DodgeFireBall"))
-            .add_systems(OnEnter(GameState::GameOver), store_new_record);
+            .add_systems(OnRunEnd, reset_score)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                store_new_records.after(finish_recording),
+            );
+        let (headless, score_dir) = app
+            .world
+            .get_resource::<GameConfig>()
+            .map(|config| (config.headless, config.score_dir.clone()))
+            .unwrap_or_default();
+        // Headless apps never touch the player's records unless told where to keep them
+        app.insert_resource(match score_dir {
+            Some(dir) => ScoreStorage::new(FileScoreStore::new(dir)),
+            None if headless => ScoreStorage::new(MemoryScoreStore::default()),
+            None => ScoreStorage::platform_default(),
+        });
     }
 }
 
@@ -33,8 +60,10 @@ impl Default for GameData {
     fn default() -> Self {
         Self {
             record: Default::default(),
+            best_time: Default::default(),
             n_balls: Default::default(),
-            current_fireballs_speed: INITIAL_FIREBALL_SPEED,
+            current_fireballs_speed: GameTuning::default().initial_fireball_speed,
+            lives: GameTuning::default().player_lives,
         }
     }
 }
@@ -42,23 +71,22 @@ impl Default for GameData {
 fn spawn_ui(
     mut commands: Commands,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    #[cfg(not(target_os = "android"))] mut game_data: ResMut<GameData>,
-    #[cfg(target_os = "android")] game_data: Res<GameData>,
-    #[cfg(not(target_os = "android"))] mut pkv: ResMut<PkvStore>,
+    mut game_data: ResMut<GameData>,
+    score: Res<Score>,
+    storage: Res<ScoreStorage>,
 ) {
-    #[cfg(not(target_os = "android"))]
-    if let Ok(record) = pkv.get::<u64>("best_score") {
-        game_data.record = record;
-    } else {
-        pkv.set("best_score", &0u64)
-            .expect("failed to store best score");
+    if let Some(record) = load_record(&storage, BEST_SCORE_KEY, "best score") {
+        game_data.record = record.score;
+    }
+    if let Some(record) = load_record(&storage, BEST_TIME_KEY, "best survival time") {
+        game_data.best_time = record.duration;
     }
     let window = window_query.get_single().unwrap();
     let height = window.height();
     commands.spawn((
         Text2dBundle {
             text: Text::from_section(
-                format!("{}\nHigh Score: {}", game_data.n_balls, game_data.record),
+                format!("{}\nHigh Score: {}", score.points, game_data.record),
                 TextStyle {
                     font_size: FONT_SIZE,
                     color: Color::WHITE,
@@ -76,16 +104,70 @@ fn spawn_ui(
     ));
 }
 
+fn load_record(storage: &ScoreStorage, key: &str, name: &str) -> Option<ScoreRecord> {
+    match storage.get::<SignedRecord>(key) {
+        Ok(Some(signed_record)) => {
+            let record = signed_record.verify();
+            if record.is_none() {
+                warn!("stored {name} failed verification, ignoring it");
+            }
+            record
+        }
+        Ok(None) => None,
+        Err(error) => {
+            // Older versions stored an unsigned score, which can't be trusted
+            if let Ok(Some(score)) = storage.get::<u64>(key) {
+                warn!("stored {name} {score} predates signed records and can't be verified, resetting it");
+            } else {
+                warn!("ignoring stored {name}: {error}");
+            }
+            None
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_ui(
-    mut query: Query<(&mut Text, &mut Transform), With<UiComponent>>,
+    mut query: Query<(&mut Text, &mut Transform, &mut Visibility), With<UiComponent>>,
     window_query: Query<&Window, With<PrimaryWindow>>,
     game_data: Res<GameData>,
+    score: Res<Score>,
+    clock: Res<RunClock>,
+    power_ups: Res<PowerUps>,
+    state: Res<State<GameState>>,
+    tuning: Res<GameTuning>,
 ) {
     let window = window_query.get_single().unwrap();
     let height = window.height();
-    let (mut ui_text, mut ui_transform) = query.get_single_mut().unwrap();
+    let (mut ui_text, mut ui_transform, mut ui_visibility) = query.get_single_mut().unwrap();
+    *ui_visibility = match state.get() {
+        GameState::MainMenu
+        | GameState::Settings
+        | GameState::Controls
+        | GameState::Leaderboard => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    };
+    let mut hud = format!(
+        "{}\n{}\nHigh Score: {}\nBest Time: {}",
+        score.points,
+        format_run_time(clock.elapsed()),
+        game_data.record,
+        format_run_time(game_data.best_time)
+    );
+    if score.multiplier > 1 {
+        hud.push_str(&format!("\nCombo x{}", score.multiplier));
+    }
+    if tuning.player_lives > 1 {
+        hud.push_str(&format!("\nLives: {}", game_data.lives));
+    }
+    if power_ups.shield {
+        hud.push_str("\nShield");
+    }
+    for (kind, seconds_left) in power_ups.timed_effects() {
+        hud.push_str(&format!("\n{}: {:.1}s", kind.name(), seconds_left));
+    }
     *ui_text = Text::from_section(
-        format!("{}\nHigh Score: {}", game_data.n_balls, game_data.record),
+        hud,
         TextStyle {
             font_size: FONT_SIZE,
             color: Color::WHITE,
@@ -100,11 +182,39 @@ fn reset_score(mut game_data: ResMut<GameData>) {
     game_data.n_balls = 0;
 }
 
-#[cfg(not(target_os = "android"))]
-fn store_new_record(mut pkv: ResMut<PkvStore>, mut game_data: ResMut<GameData>) {
-    if game_data.n_balls > game_data.record {
-        game_data.record = game_data.n_balls;
-        pkv.set("best_score", &game_data.n_balls)
-            .expect("failed to store best score");
+pub fn store_new_records(
+    mut storage: ResMut<ScoreStorage>,
+    mut game_data: ResMut<GameData>,
+    score: Res<Score>,
+    clock: Res<RunClock>,
+    recorder: Res<ReplayRecorder>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    let signed_record = recorder.last_run.as_ref().map(|replay| {
+        ScoreRecord {
+            score: score.points,
+            seed: replay.seed,
+            duration: clock.elapsed(),
+            replay_hash: *blake3::hash(&replay.to_bytes()).as_bytes(),
+        }
+        .sign()
+    });
+    if score.points > game_data.record {
+        game_data.record = score.points;
+        run_stats.new_record = true;
+        if let Some(signed_record) = &signed_record {
+            if let Err(error) = storage.set(BEST_SCORE_KEY, signed_record) {
+                error!("failed to store best score: {error}");
+            }
+        }
+    }
+    if clock.elapsed() > game_data.best_time {
+        game_data.best_time = clock.elapsed();
+        run_stats.new_best_time = true;
+        if let Some(signed_record) = &signed_record {
+            if let Err(error) = storage.set(BEST_TIME_KEY, signed_record) {
+                error!("failed to store best survival time: {error}");
+            }
+        }
     }
 }
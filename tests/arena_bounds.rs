@@ -0,0 +1,78 @@
+mod common;
+
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+use bevy_rapier2d::dynamics::Velocity;
+use common::{in_game_app, primary_window};
+use dodge_fire_ball::{tuning::GameTuning, GameConfig};
+
+fn arena_app(seed: u64) -> App {
+    in_game_app(GameConfig {
+        seed: Some(seed),
+        // Fill the arena quickly with fast fireballs
+        tuning: GameTuning {
+            fireball_spawn_time: 0.25,
+            spawn_warning_time: 0.0,
+            initial_fireball_speed: 400.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    })
+}
+
+/// Steps the app one frame at a time, checking every moving body is inside the window.
+fn assert_bodies_stay_inside(app: &mut App, frames: u32) -> usize {
+    let mut max_bodies = 0;
+    for frame in 0..frames {
+        app.update();
+        let window = app
+            .world
+            .query_filtered::<&Window, With<PrimaryWindow>>()
+            .single(&app.world);
+        let half_size = Vec2::new(window.width(), window.height()) / 2.0;
+        let mut bodies = app.world.query_filtered::<&Transform, With<Velocity>>();
+        let mut count = 0;
+        for transform in bodies.iter(&app.world) {
+            let position = transform.translation.xy();
+            assert!(
+                position.abs().cmple(half_size).all(),
+                "body escaped to {position} at frame {frame}, window half size is {half_size}"
+            );
+            count += 1;
+        }
+        max_bodies = max_bodies.max(count);
+    }
+    max_bodies
+}
+
+#[test]
+fn fireballs_never_escape_the_window() {
+    for seed in [1, 2, 3] {
+        let mut app = arena_app(seed);
+        let max_bodies = assert_bodies_stay_inside(&mut app, 60 * 20);
+        assert!(max_bodies > 1, "no fireball was spawned with seed {seed}");
+    }
+}
+
+#[test]
+fn walls_follow_the_resized_window() {
+    let mut app = arena_app(4);
+    assert_bodies_stay_inside(&mut app, 60 * 5);
+
+    let window = primary_window(&mut app);
+    let (width, height) = (600.0, 400.0);
+    app.world
+        .get_mut::<Window>(window)
+        .unwrap()
+        .resolution
+        .set(width, height);
+    // Sent by winit in the windowed game
+    app.world.send_event(WindowResized {
+        window,
+        width,
+        height,
+    });
+    assert_bodies_stay_inside(&mut app, 60 * 10);
+}
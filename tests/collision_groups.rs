@@ -0,0 +1,44 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier2d::geometry::CollisionGroups;
+use common::in_game_app;
+use dodge_fire_ball::{
+    collision::{fireball_groups, FireballCollisionMode, Hazard},
+    step_frames,
+    tuning::GameTuning,
+    GameConfig,
+};
+
+fn spawned_fireball_groups(mode: FireballCollisionMode) -> CollisionGroups {
+    let mut app = in_game_app(GameConfig {
+        fireball_collisions: mode,
+        tuning: GameTuning {
+            fireball_spawn_time: 0.25,
+            spawn_warning_time: 0.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    });
+    step_frames(&mut app, 30);
+    *app.world
+        .query_filtered::<&CollisionGroups, With<Hazard>>()
+        .iter(&app.world)
+        .next()
+        .expect("a fireball spawned")
+}
+
+#[test]
+fn fireballs_follow_the_collision_mode() {
+    for mode in [
+        FireballCollisionMode::PassThrough,
+        FireballCollisionMode::Bounce,
+    ] {
+        assert_eq!(spawned_fireball_groups(mode), fireball_groups(mode));
+    }
+    let args = ["--fireball-collisions", "pass-through"].map(String::from);
+    assert_eq!(
+        GameConfig::parse_args(args).unwrap().fireball_collisions,
+        FireballCollisionMode::PassThrough
+    );
+}
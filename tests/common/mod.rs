@@ -0,0 +1,179 @@
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_rapier2d::{
+    dynamics::Velocity, pipeline::CollisionEvent, rapier::geometry::CollisionEventFlags,
+};
+use dodge_fire_ball::{
+    build_game_app, collision::Hazard, menu::MenuItem, player::Player, state::GameState,
+    step_frames, tuning::GameTuning, GameConfig,
+};
+
+/// Directory in the system temp dir owned by a single test, removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "dodge_fire_ball_{name}_{}_{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Builds the game with `config` and lets its startup systems run.
+pub fn app_with(config: GameConfig) -> App {
+    let mut app = build_game_app(config);
+    step_frames(&mut app, 5);
+    app
+}
+
+pub fn headless_app() -> App {
+    app_with(GameConfig::headless())
+}
+
+/// Builds the game with `config` and presses Play in the main menu.
+pub fn in_game_app(config: GameConfig) -> App {
+    let mut app = app_with(config);
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::InGame);
+    app
+}
+
+pub fn primary_window(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .single(&app.world)
+}
+
+/// Presses and releases a key, stepping two frames after each.
+pub fn tap_key(app: &mut App, key_code: KeyCode, logical_key: Key) {
+    let window = primary_window(app);
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world.send_event(KeyboardInput {
+            key_code,
+            logical_key: logical_key.clone(),
+            state,
+            window,
+        });
+        step_frames(app, 2);
+    }
+}
+
+pub fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+pub fn player(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, With<Player>>()
+        .single(&app.world)
+}
+
+/// Has a hazard run into the player from its right, as rapier would report it.
+pub fn hit_player(app: &mut App) {
+    let player = player(app);
+    let position = app.world.get::<Transform>(player).unwrap().translation;
+    let hazard = app
+        .world
+        .spawn((
+            Hazard,
+            Transform::from_translation(position + Vec3::X * 20.0),
+        ))
+        .id();
+    app.world.send_event(CollisionEvent::Started(
+        player,
+        hazard,
+        CollisionEventFlags::empty(),
+    ));
+    step_frames(app, 2);
+}
+
+pub fn player_velocity(app: &mut App) -> Vec2 {
+    app.world
+        .query_filtered::<&Velocity, With<Player>>()
+        .single(&app.world)
+        .linvel
+}
+
+/// Headless config whose runs fill up with fast fireballs right away, so the
+/// player doesn't survive long.
+pub fn deadly_config(seed: u64) -> GameConfig {
+    GameConfig {
+        seed: Some(seed),
+        tuning: GameTuning {
+            fireball_spawn_time: 0.25,
+            spawn_warning_time: 0.0,
+            initial_fireball_speed: 400.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    }
+}
+
+/// Steps the app until the run is over, and returns the frames it took.
+pub fn play_until_game_over(app: &mut App, max_frames: u32) -> u32 {
+    for frame in 0..max_frames {
+        if state(app) == GameState::GameOver {
+            return frame;
+        }
+        app.update();
+    }
+    panic!("the run was still going after {max_frames} frames");
+}
+
+/// Texts of every UI node, menus included.
+pub fn menu_texts(app: &mut App) -> Vec<String> {
+    app.world
+        .query::<&Text>()
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect()
+}
+
+/// Presses the menu item whose label starts with `label`, like a tap would.
+pub fn press_item(app: &mut App, label: &str) {
+    let mut query = app
+        .world
+        .query_filtered::<(Entity, &Children), With<MenuItem>>();
+    let item = query
+        .iter(&app.world)
+        .find(|(_, children)| {
+            children.iter().any(|child| {
+                app.world
+                    .get::<Text>(*child)
+                    .is_some_and(|text| text.sections[0].value.starts_with(label))
+            })
+        })
+        .map(|(item, _)| item)
+        .unwrap_or_else(|| panic!("no `{label}` item in {:?}", menu_texts(app)));
+    *app.world.get_mut::<Interaction>(item).unwrap() = Interaction::Pressed;
+    step_frames(app, 2);
+}
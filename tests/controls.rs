@@ -0,0 +1,74 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{app_with, state, tap_key, TempDir};
+use dodge_fire_ball::{
+    actions::{Action, InputBindings},
+    menu::PendingRebinding,
+    state::GameState,
+    step_frames,
+    storage::ScoreStorage,
+    GameConfig,
+};
+
+fn controls_app(dir: &TempDir) -> App {
+    let mut app = app_with(GameConfig {
+        score_dir: Some(dir.path().to_path_buf()),
+        ..GameConfig::headless()
+    });
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Controls);
+    step_frames(&mut app, 2);
+    assert_eq!(state(&app), GameState::Controls);
+    app
+}
+
+#[test]
+fn captured_key_is_bound_and_stored() {
+    let dir = TempDir::new("controls_capture");
+    let mut app = controls_app(&dir);
+
+    // The first item rebinds Move Up
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(
+        app.world.resource::<PendingRebinding>().0,
+        Some(Action::MoveUp)
+    );
+    tap_key(&mut app, KeyCode::KeyI, Key::Character("i".into()));
+    assert_eq!(app.world.resource::<PendingRebinding>().0, None);
+    let bindings = app.world.resource::<InputBindings>().clone();
+    assert_eq!(bindings.get(Action::MoveUp).keys, [KeyCode::KeyI]);
+    let stored = app
+        .world
+        .resource::<ScoreStorage>()
+        .get::<InputBindings>("input_bindings")
+        .unwrap();
+    assert_eq!(stored, Some(bindings.clone()));
+    // The captured key didn't move the selection nor rebind again
+    assert_eq!(state(&app), GameState::Controls);
+
+    // Loaded back on the next launch
+    let app = controls_app(&dir);
+    assert_eq!(*app.world.resource::<InputBindings>(), bindings);
+}
+
+#[test]
+fn escape_cancels_a_rebinding() {
+    let dir = TempDir::new("controls_cancel");
+    let mut app = controls_app(&dir);
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(app.world.resource::<PendingRebinding>().0, None);
+    assert_eq!(
+        *app.world.resource::<InputBindings>(),
+        InputBindings::default()
+    );
+    assert_eq!(
+        app.world
+            .resource::<ScoreStorage>()
+            .get::<InputBindings>("input_bindings")
+            .unwrap(),
+        None
+    );
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use bevy_rapier2d::geometry::CollisionGroups;
+use common::{in_game_app, player_velocity, tap_key};
+use dodge_fire_ball::{
+    collision::{invulnerable_player_groups, player_groups},
+    dash::{DashCooldown, Dashing},
+    player::Player,
+    stats::RunStats,
+    step_frames,
+    tuning::GameTuning,
+    GameConfig,
+};
+
+fn dash(app: &mut App) {
+    tap_key(app, KeyCode::ShiftLeft, Key::Shift);
+}
+
+fn dashes(app: &App) -> u32 {
+    app.world.resource::<RunStats>().dashes
+}
+
+#[test]
+fn dashes_wait_for_the_cooldown() {
+    let mut app = in_game_app(GameConfig {
+        tuning: GameTuning {
+            dash_speed: 450.0,
+            dash_duration: 0.25,
+            dash_cooldown: 1.0,
+            fireball_spawn_time: 1000.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    });
+    let player = app
+        .world
+        .query_filtered::<Entity, With<Player>>()
+        .single(&app.world);
+
+    dash(&mut app);
+    assert_eq!(dashes(&app), 1);
+    assert!(app.world.get::<Dashing>(player).is_some());
+    assert_eq!(
+        app.world.get::<CollisionGroups>(player),
+        Some(&invulnerable_player_groups())
+    );
+    assert!((player_velocity(&mut app).length() - 450.0).abs() < 1e-3);
+
+    step_frames(&mut app, 20);
+    assert!(app.world.get::<Dashing>(player).is_none());
+    assert_eq!(
+        app.world.get::<CollisionGroups>(player),
+        Some(&player_groups())
+    );
+    assert!((player_velocity(&mut app).length() - 100.0).abs() < 1e-3);
+
+    // Still cooling down
+    assert!(!app.world.resource::<DashCooldown>().ready());
+    dash(&mut app);
+    assert_eq!(dashes(&app), 1);
+    assert!(app.world.get::<Dashing>(player).is_none());
+
+    step_frames(&mut app, 40);
+    assert!(app.world.resource::<DashCooldown>().ready());
+    dash(&mut app);
+    assert_eq!(dashes(&app), 2);
+}
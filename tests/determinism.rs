@@ -0,0 +1,29 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use bevy_rapier2d::dynamics::Velocity;
+use common::{deadly_config, in_game_app, tap_key};
+use dodge_fire_ball::{collision::Hazard, step_frames};
+
+/// Plays the same inputs for 10 seconds, then returns the position and the
+/// velocity of every fireball.
+fn fireballs_after_run(seed: u64) -> Vec<(Vec3, Vec2)> {
+    let mut app = in_game_app(deadly_config(seed));
+    tap_key(&mut app, KeyCode::ArrowRight, Key::ArrowRight);
+    step_frames(&mut app, 120);
+    tap_key(&mut app, KeyCode::ArrowDown, Key::ArrowDown);
+    step_frames(&mut app, 480);
+    app.world
+        .query_filtered::<(&Transform, &Velocity), With<Hazard>>()
+        .iter(&app.world)
+        .map(|(transform, velocity)| (transform.translation, velocity.linvel))
+        .collect()
+}
+
+#[test]
+fn same_seed_and_inputs_give_the_same_fireballs() {
+    let fireballs = fireballs_after_run(5);
+    assert!(fireballs.len() > 1);
+    assert_eq!(fireballs_after_run(5), fireballs);
+    assert_ne!(fireballs_after_run(6), fireballs);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{
+    deadly_config, in_game_app, menu_texts, play_until_game_over, press_item, state, tap_key,
+};
+use dodge_fire_ball::{
+    explosion::ExplosionEndedEvent,
+    state::{GameState, StateFlags},
+    step_frames,
+    ui::GameData,
+    GameConfig,
+};
+
+#[test]
+fn headless_run_plays_until_game_over() {
+    let mut app = in_game_app(deadly_config(3));
+    let mut explosion_events = app
+        .world
+        .resource::<Events<ExplosionEndedEvent>>()
+        .get_reader();
+    play_until_game_over(&mut app, 60 * 120);
+    assert!(app.world.resource::<GameData>().n_balls > 0);
+    assert!(!app.world.resource::<StateFlags>().explosion_ended);
+
+    // The explosion of the player plays before the run can be retried
+    let mut frames = 0;
+    while !app.world.resource::<StateFlags>().explosion_ended {
+        assert!(frames < 600, "the explosion never ended");
+        app.update();
+        frames += 1;
+    }
+    assert!(frames > 1);
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(
+        explosion_events
+            .read(app.world.resource::<Events<ExplosionEndedEvent>>())
+            .count(),
+        1
+    );
+
+    // The first run always makes it to the empty leaderboard
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::GameOver);
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::InGame);
+    assert_eq!(app.world.resource::<GameData>().n_balls, 0);
+}
+
+// Ends the run right away, with a score too low for the leaderboard
+fn game_over_app() -> App {
+    let mut app = in_game_app(GameConfig::headless());
+    app.world.resource_mut::<GameData>().n_balls = 7;
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::GameOver);
+    step_frames(&mut app, 2);
+    app
+}
+
+#[test]
+fn summary_waits_for_the_explosion() {
+    let mut app = game_over_app();
+    step_frames(&mut app, 30);
+    assert!(!menu_texts(&mut app).contains(&"Game Over".to_string()));
+    // Escape doesn't retry either
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(state(&app), GameState::GameOver);
+
+    app.world.resource_mut::<StateFlags>().explosion_ended = true;
+    step_frames(&mut app, 2);
+    let texts = menu_texts(&mut app);
+    for expected in [
+        "Game Over",
+        "Fireballs survived: 7",
+        "Press Esc / tap to retry",
+    ] {
+        assert!(texts.contains(&expected.to_string()), "{texts:?}");
+    }
+    for prefix in ["Time survived: ", "Max fireball speed: "] {
+        assert!(
+            texts.iter().any(|text| text.starts_with(prefix)),
+            "{texts:?}"
+        );
+    }
+
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(state(&app), GameState::InGame);
+    assert_eq!(app.world.resource::<GameData>().n_balls, 0);
+}
+
+#[test]
+fn summary_leads_back_to_the_main_menu() {
+    let mut app = game_over_app();
+    app.world.resource_mut::<StateFlags>().explosion_ended = true;
+    step_frames(&mut app, 2);
+    press_item(&mut app, "Main Menu");
+    assert_eq!(state(&app), GameState::MainMenu);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use bevy::{
+    input::gamepad::{
+        GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+        GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+    },
+    prelude::*,
+};
+use common::{headless_app, player_velocity, state};
+use dodge_fire_ball::{state::GameState, step_frames};
+
+const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+fn connect(app: &mut App, connection: GamepadConnection) {
+    app.world
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            GAMEPAD, connection,
+        )));
+    app.update();
+}
+
+fn tap_button(app: &mut App, button_type: GamepadButtonType) {
+    for value in [1.0, 0.0] {
+        app.world
+            .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                GAMEPAD,
+                button_type,
+                value,
+            )));
+        step_frames(app, 2);
+    }
+}
+
+fn move_stick(app: &mut App, x: f32, y: f32) {
+    for (axis_type, value) in [
+        (GamepadAxisType::LeftStickX, x),
+        (GamepadAxisType::LeftStickY, y),
+    ] {
+        app.world
+            .send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+                GAMEPAD, axis_type, value,
+            )));
+    }
+    step_frames(app, 3);
+}
+
+#[test]
+fn gamepad_drives_menus_and_player() {
+    let mut app = headless_app();
+    connect(
+        &mut app,
+        GamepadConnection::Connected(GamepadInfo {
+            name: "Test pad".to_string(),
+        }),
+    );
+    assert_eq!(app.world.resource::<Gamepads>().iter().count(), 1);
+
+    // Play is the first item of the main menu
+    tap_button(&mut app, GamepadButtonType::South);
+    assert_eq!(state(&app), GameState::InGame);
+
+    let initial_velocity = player_velocity(&mut app);
+    move_stick(&mut app, 0.1, 0.05);
+    assert_eq!(player_velocity(&mut app), initial_velocity);
+
+    move_stick(&mut app, 1.0, 0.0);
+    let velocity = player_velocity(&mut app);
+    assert!(velocity.x > 0.0 && velocity.y.abs() < 1e-3, "{velocity}");
+
+    tap_button(&mut app, GamepadButtonType::Start);
+    assert_eq!(state(&app), GameState::Paused);
+    tap_button(&mut app, GamepadButtonType::Start);
+    assert_eq!(state(&app), GameState::InGame);
+}
+
+#[test]
+fn gamepads_can_be_unplugged_and_plugged_back() {
+    let mut app = headless_app();
+    let info = GamepadInfo {
+        name: "Test pad".to_string(),
+    };
+    connect(&mut app, GamepadConnection::Connected(info.clone()));
+    connect(&mut app, GamepadConnection::Disconnected);
+    assert_eq!(app.world.resource::<Gamepads>().iter().count(), 0);
+
+    // Buttons of an unplugged gamepad do nothing
+    tap_button(&mut app, GamepadButtonType::South);
+    assert_eq!(state(&app), GameState::MainMenu);
+
+    connect(&mut app, GamepadConnection::Connected(info));
+    tap_button(&mut app, GamepadButtonType::South);
+    assert_eq!(state(&app), GameState::InGame);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use bevy::{
+    input::{mouse::MouseButtonInput, touch::TouchPhase, ButtonState},
+    prelude::*,
+};
+use common::{player_velocity, primary_window};
+use dodge_fire_ball::{
+    joystick::{VirtualJoystick, JOYSTICK_RADIUS},
+    step_frames, GameConfig,
+};
+
+fn in_game_app() -> App {
+    common::in_game_app(GameConfig::headless())
+}
+
+fn move_cursor(app: &mut App, position: Vec2) {
+    let window = primary_window(app);
+    app.world.send_event(CursorMoved {
+        window,
+        position,
+        delta: None,
+    });
+    step_frames(app, 2);
+}
+
+fn left_mouse_button(app: &mut App, state: ButtonState) {
+    let window = primary_window(app);
+    app.world.send_event(MouseButtonInput {
+        button: MouseButton::Left,
+        state,
+        window,
+    });
+    step_frames(app, 2);
+}
+
+fn touch(app: &mut App, id: u64, phase: TouchPhase, position: Vec2) {
+    let window = primary_window(app);
+    app.world.send_event(TouchInput {
+        phase,
+        position,
+        window,
+        force: None,
+        id,
+    });
+    step_frames(app, 2);
+}
+
+#[test]
+fn mouse_drag_steers_through_the_joystick() {
+    let mut app = in_game_app();
+    let origin = Vec2::new(300.0, 300.0);
+    move_cursor(&mut app, origin);
+    left_mouse_button(&mut app, ButtonState::Pressed);
+    let joystick = app.world.resource::<VirtualJoystick>();
+    assert_eq!(joystick.origin(), Some(origin));
+    assert_eq!(joystick.value(), Some(Vec2::ZERO));
+
+    // Dragging past the radius tilts the joystick fully, screen y grows downward
+    move_cursor(&mut app, origin + Vec2::new(0.0, -2.0 * JOYSTICK_RADIUS));
+    let value = app.world.resource::<VirtualJoystick>().value().unwrap();
+    assert!((value - Vec2::Y).length() < 1e-5, "{value}");
+    let velocity = player_velocity(&mut app);
+    assert!(velocity.y > 0.0 && velocity.x.abs() < 1e-3, "{velocity}");
+
+    // A half tilt to the right still steers the player right
+    move_cursor(&mut app, origin + Vec2::new(JOYSTICK_RADIUS / 2.0, 0.0));
+    let value = app.world.resource::<VirtualJoystick>().value().unwrap();
+    assert!((value - Vec2::new(0.5, 0.0)).length() < 1e-5, "{value}");
+    let velocity = player_velocity(&mut app);
+    assert!(velocity.x > 0.0 && velocity.y.abs() < 1e-3, "{velocity}");
+
+    left_mouse_button(&mut app, ButtonState::Released);
+    assert_eq!(app.world.resource::<VirtualJoystick>().value(), None);
+}
+
+#[test]
+fn joystick_follows_the_first_finger_only() {
+    let mut app = in_game_app();
+    let origin = Vec2::new(200.0, 400.0);
+    touch(&mut app, 1, TouchPhase::Started, origin);
+    touch(&mut app, 2, TouchPhase::Started, Vec2::new(600.0, 100.0));
+    touch(&mut app, 2, TouchPhase::Moved, Vec2::new(700.0, 100.0));
+    touch(
+        &mut app,
+        1,
+        TouchPhase::Moved,
+        origin - Vec2::X * JOYSTICK_RADIUS,
+    );
+    let joystick = app.world.resource::<VirtualJoystick>();
+    assert_eq!(joystick.origin(), Some(origin));
+    assert_eq!(joystick.value(), Some(Vec2::NEG_X));
+    assert!(player_velocity(&mut app).x < 0.0);
+
+    touch(&mut app, 2, TouchPhase::Ended, Vec2::new(700.0, 100.0));
+    assert!(app.world.resource::<VirtualJoystick>().value().is_some());
+    touch(&mut app, 1, TouchPhase::Ended, origin);
+    assert_eq!(app.world.resource::<VirtualJoystick>().value(), None);
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{in_game_app, menu_texts, press_item, state, tap_key, TempDir};
+use dodge_fire_ball::{
+    difficulty::DifficultyPreset,
+    leaderboard::{
+        format_date, Leaderboard, LeaderboardEntry, NameEntry, StoredLeaderboard, DEFAULT_NAME,
+        LEADERBOARD_KEY, LEADERBOARD_SIZE,
+    },
+    score::Score,
+    state::{GameState, StateFlags},
+    step_frames,
+    storage::{FileScoreStore, ScoreStorage},
+    GameConfig,
+};
+
+fn entry(name: &str, score: u64) -> LeaderboardEntry {
+    LeaderboardEntry {
+        name: name.to_string(),
+        score,
+        time_survived: Duration::from_secs(score),
+        date: 0,
+        difficulty: DifficultyPreset::Normal,
+        seed: score,
+    }
+}
+
+#[test]
+fn keeps_the_top_runs_in_order() {
+    let mut leaderboard = Leaderboard::default();
+    assert!(!leaderboard.qualifies(0));
+    for score in 1..=LEADERBOARD_SIZE as u64 {
+        assert!(leaderboard.qualifies(score));
+        leaderboard.insert(entry("run", score * 10));
+    }
+    assert!(!leaderboard.qualifies(10));
+    assert!(leaderboard.qualifies(11));
+
+    // Ties go after the runs already there
+    assert_eq!(leaderboard.insert(entry("tie", 50)), Some(6));
+    assert_eq!(leaderboard.insert(entry("top", 1000)), Some(0));
+    assert_eq!(leaderboard.insert(entry("low", 5)), None);
+    assert_eq!(leaderboard.entries.len(), LEADERBOARD_SIZE);
+    let scores: Vec<_> = leaderboard
+        .entries
+        .iter()
+        .map(|entry| entry.score)
+        .collect();
+    assert_eq!(scores, [1000, 100, 90, 80, 70, 60, 50, 50, 40, 30]);
+}
+
+#[test]
+fn loads_what_was_stored() {
+    let dir = TempDir::new("leaderboard_store");
+    let mut storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    assert_eq!(Leaderboard::load(&storage), Leaderboard::default());
+
+    let mut leaderboard = Leaderboard::default();
+    leaderboard.insert(entry("first", 300));
+    leaderboard.insert(entry("second", 200));
+    leaderboard.store(&mut storage);
+    assert_eq!(Leaderboard::load(&storage), leaderboard);
+
+    // Out of order entries are sorted when loaded
+    let stored = StoredLeaderboard::V1(vec![entry("b", 1), entry("a", 2)]);
+    storage.set(LEADERBOARD_KEY, &stored).unwrap();
+    let loaded = Leaderboard::load(&storage);
+    assert_eq!(loaded.entries, [entry("a", 2), entry("b", 1)]);
+}
+
+#[test]
+fn formats_dates() {
+    assert_eq!(format_date(0), "1970-01-01");
+    assert_eq!(format_date(951_782_400), "2000-02-29");
+    assert_eq!(format_date(1_735_689_599), "2024-12-31");
+}
+
+// Ends a run scoring enough for the leaderboard
+fn qualifying_run_over(dir: &TempDir) -> App {
+    let mut app = in_game_app(GameConfig {
+        score_dir: Some(dir.path().to_path_buf()),
+        ..GameConfig::headless()
+    });
+    app.world.resource_mut::<Score>().points = 1234;
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::GameOver);
+    step_frames(&mut app, 2);
+    assert!(app.world.contains_resource::<NameEntry>());
+    app
+}
+
+// Same, once its explosion is over
+fn qualifying_game_over(dir: &TempDir) -> App {
+    let mut app = qualifying_run_over(dir);
+    app.world.resource_mut::<StateFlags>().explosion_ended = true;
+    step_frames(&mut app, 2);
+    app
+}
+
+#[test]
+fn qualifying_run_asks_for_a_name() {
+    let dir = TempDir::new("leaderboard_entry");
+    let mut app = qualifying_game_over(&dir);
+
+    tap_key(&mut app, KeyCode::KeyA, Key::Character("A".into()));
+    tap_key(&mut app, KeyCode::KeyX, Key::Character("x".into()));
+    tap_key(&mut app, KeyCode::Backspace, Key::Backspace);
+    tap_key(&mut app, KeyCode::KeyL, Key::Character("l".into()));
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+
+    // Enter saves the name instead of retrying
+    assert_eq!(state(&app), GameState::GameOver);
+    assert!(!app.world.contains_resource::<NameEntry>());
+    let entries = &app.world.resource::<Leaderboard>().entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "Al");
+    assert_eq!(entries[0].score, 1234);
+
+    let storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    assert_eq!(Leaderboard::load(&storage).entries, *entries);
+}
+
+#[test]
+fn name_saved_as_the_explosion_ends_makes_room_for_the_summary() {
+    let dir = TempDir::new("leaderboard_early");
+    let mut app = qualifying_run_over(&dir);
+    // Enter lands in the frame the prompt shows up
+    app.world.resource_mut::<StateFlags>().explosion_ended = true;
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+
+    assert_eq!(app.world.resource::<Leaderboard>().entries.len(), 1);
+    let texts = menu_texts(&mut app);
+    assert!(texts.contains(&"Game Over".to_string()), "{texts:?}");
+    assert!(!texts.iter().any(|text| text.starts_with("Save as")));
+}
+
+#[test]
+fn escape_skips_the_name_entry() {
+    let dir = TempDir::new("leaderboard_skip");
+    let mut app = qualifying_game_over(&dir);
+    tap_key(&mut app, KeyCode::KeyA, Key::Character("A".into()));
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+
+    assert_eq!(state(&app), GameState::GameOver);
+    assert!(!app.world.contains_resource::<NameEntry>());
+    assert!(app.world.resource::<Leaderboard>().entries.is_empty());
+    let storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    assert_eq!(Leaderboard::load(&storage), Leaderboard::default());
+    // The usual panel takes over
+    let texts = menu_texts(&mut app);
+    assert!(texts.contains(&"Game Over".to_string()), "{texts:?}");
+    assert!(!texts.iter().any(|text| text.starts_with("Save as")));
+}
+
+#[test]
+fn players_without_a_keyboard_save_the_default_name() {
+    let dir = TempDir::new("leaderboard_tap");
+    let mut app = qualifying_game_over(&dir);
+    press_item(&mut app, "Save as");
+
+    assert_eq!(state(&app), GameState::GameOver);
+    assert!(!app.world.contains_resource::<NameEntry>());
+    let entries = &app.world.resource::<Leaderboard>().entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, DEFAULT_NAME);
+
+    // Or leave without an entry
+    let dir = TempDir::new("leaderboard_tap_skip");
+    let mut app = qualifying_game_over(&dir);
+    press_item(&mut app, "Skip");
+    assert!(!app.world.contains_resource::<NameEntry>());
+    assert!(app.world.resource::<Leaderboard>().entries.is_empty());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier2d::geometry::CollisionGroups;
+use common::{hit_player, in_game_app, player, player_velocity, state};
+use dodge_fire_ball::{
+    collision::{invulnerable_player_groups, player_groups},
+    player::Invulnerable,
+    state::GameState,
+    step_frames,
+    tuning::GameTuning,
+    ui::GameData,
+    GameConfig,
+};
+
+fn lives(app: &App) -> u32 {
+    app.world.resource::<GameData>().lives
+}
+
+#[test]
+fn hits_take_lives_with_invulnerability_in_between() {
+    let mut app = in_game_app(GameConfig {
+        tuning: GameTuning {
+            player_lives: 3,
+            invulnerability_time: 0.5,
+            // Only the hits of the test
+            fireball_spawn_time: 1000.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    });
+    let player = player(&mut app);
+    assert_eq!(lives(&app), 3);
+
+    hit_player(&mut app);
+    assert_eq!(lives(&app), 2);
+    assert!(app.world.get::<Invulnerable>(player).is_some());
+    assert_eq!(
+        app.world.get::<CollisionGroups>(player),
+        Some(&invulnerable_player_groups())
+    );
+    // Knocked back away from the hazard, give or take the player's own drift
+    let velocity = player_velocity(&mut app);
+    assert!((velocity.length() - 250.0).abs() < 1e-2, "{velocity}");
+    assert!(velocity.x < -200.0, "{velocity}");
+
+    // Ignored while invulnerable
+    hit_player(&mut app);
+    assert_eq!(lives(&app), 2);
+
+    step_frames(&mut app, 40);
+    assert!(app.world.get::<Invulnerable>(player).is_none());
+    assert_eq!(
+        app.world.get::<CollisionGroups>(player),
+        Some(&player_groups())
+    );
+    hit_player(&mut app);
+    assert_eq!(lives(&app), 1);
+    assert_eq!(state(&app), GameState::InGame);
+
+    step_frames(&mut app, 40);
+    hit_player(&mut app);
+    assert_eq!(lives(&app), 0);
+    assert_eq!(state(&app), GameState::GameOver);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{app_with, deadly_config, state, tap_key};
+use dodge_fire_ball::{collision::Hazard, state::GameState, step_frames, ui::GameData};
+
+fn hazards(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<Hazard>>()
+        .iter(&app.world)
+        .count()
+}
+
+#[test]
+fn fireballs_wait_for_play() {
+    // A fireball every 15 frames once the run starts
+    let mut app = app_with(deadly_config(5));
+    assert_eq!(state(&app), GameState::MainMenu);
+    step_frames(&mut app, 120);
+    for menu in [GameState::Settings, GameState::Leaderboard] {
+        app.world.resource_mut::<NextState<GameState>>().set(menu);
+        step_frames(&mut app, 60);
+    }
+    assert_eq!(hazards(&mut app), 0);
+    assert_eq!(app.world.resource::<GameData>().n_balls, 0);
+
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::MainMenu);
+    step_frames(&mut app, 2);
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::InGame);
+    step_frames(&mut app, 60);
+    assert!(hazards(&mut app) > 0);
+    assert!(app.world.resource::<GameData>().n_balls > 0);
+}
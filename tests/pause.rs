@@ -0,0 +1,45 @@
+mod common;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{in_game_app, state, tap_key};
+use dodge_fire_ball::{
+    state::GameState, step_frames, tuning::GameTuning, ui::GameData, GameConfig,
+};
+
+#[test]
+fn pause_freezes_time_and_spawns() {
+    // Frequent but slow fireballs, so the run outlasts the test
+    let mut app = in_game_app(GameConfig {
+        seed: Some(8),
+        tuning: GameTuning {
+            fireball_spawn_time: 0.25,
+            spawn_warning_time: 0.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    });
+    step_frames(&mut app, 20);
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(state(&app), GameState::Paused);
+
+    let virtual_time = app.world.resource::<Time<Virtual>>().elapsed();
+    let fixed_time = app.world.resource::<Time<Fixed>>().elapsed();
+    let n_balls = app.world.resource::<GameData>().n_balls;
+    assert!(app.world.resource::<Time<Virtual>>().is_paused());
+    // Long enough for several fireballs if the spawn timer kept going
+    step_frames(&mut app, 120);
+    assert_eq!(
+        app.world.resource::<Time<Virtual>>().elapsed(),
+        virtual_time
+    );
+    assert_eq!(app.world.resource::<Time<Fixed>>().elapsed(), fixed_time);
+    assert_eq!(app.world.resource::<GameData>().n_balls, n_balls);
+
+    // Resume is the first item of the pause menu
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::InGame);
+    assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+    step_frames(&mut app, 30);
+    assert!(app.world.resource::<Time<Virtual>>().elapsed() > virtual_time);
+    assert!(app.world.resource::<GameData>().n_balls > n_balls);
+}
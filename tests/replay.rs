@@ -0,0 +1,131 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{deadly_config, in_game_app, play_until_game_over, tap_key, TempDir};
+use dodge_fire_ball::{
+    replay::{Replay, ReplayError, ReplayFrame, ReplayMode, ReplayRecorder, MAX_REPLAY_FRAMES},
+    step_frames,
+    ui::GameData,
+    GameConfig,
+};
+
+const MAX_RUN_FRAMES: u32 = 60 * 120;
+
+fn header(version: u8, runs: u32) -> Vec<u8> {
+    let mut bytes = b"DFBR".to_vec();
+    bytes.push(version);
+    bytes.extend_from_slice(&7u64.to_le_bytes());
+    bytes.extend_from_slice(&16_666_667u64.to_le_bytes());
+    bytes.extend_from_slice(&runs.to_le_bytes());
+    bytes
+}
+
+fn push_run(bytes: &mut Vec<u8>, count: u32) {
+    bytes.extend_from_slice(&count.to_le_bytes());
+    bytes.push(0);
+    bytes.push(0);
+}
+
+#[test]
+fn replays_survive_the_round_trip() {
+    let replay = Replay {
+        seed: 42,
+        timestep: Duration::from_nanos(16_666_667),
+        frames: vec![
+            ReplayFrame::default(),
+            ReplayFrame::default(),
+            ReplayFrame {
+                keys: 0b1_0001,
+                axis: None,
+            },
+            ReplayFrame {
+                keys: 0,
+                axis: Some(Vec2::new(0.5, -1.0)),
+            },
+        ],
+    };
+    let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+    assert_eq!(loaded, replay);
+}
+
+#[test]
+fn malformed_replays_are_rejected() {
+    assert!(matches!(
+        Replay::from_bytes(b"NOPE"),
+        Err(ReplayError::BadMagic)
+    ));
+    assert!(matches!(
+        Replay::from_bytes(&header(2, 1)),
+        Err(ReplayError::Truncated)
+    ));
+    // Version 1 stored touch positions the game can't steer with anymore
+    let mut bytes = header(1, 1);
+    push_run(&mut bytes, 1);
+    assert!(matches!(
+        Replay::from_bytes(&bytes),
+        Err(ReplayError::UnsupportedVersion(1))
+    ));
+}
+
+#[test]
+fn oversized_replays_are_rejected_before_allocating() {
+    let mut bytes = header(2, 1);
+    push_run(&mut bytes, u32::MAX);
+    assert!(matches!(
+        Replay::from_bytes(&bytes),
+        Err(ReplayError::TooLong)
+    ));
+
+    // Runs that only add up past the limit
+    let mut bytes = header(2, 2);
+    push_run(&mut bytes, MAX_REPLAY_FRAMES as u32);
+    push_run(&mut bytes, 1);
+    assert!(matches!(
+        Replay::from_bytes(&bytes),
+        Err(ReplayError::TooLong)
+    ));
+}
+
+#[test]
+fn bad_replay_files_are_reported_on_the_command_line() {
+    let dir = TempDir::new("replay_cli");
+    fs::create_dir_all(dir.path()).unwrap();
+    let path = dir.path().join("bad.replay");
+    fs::write(&path, b"DFBR").unwrap();
+    let args = ["--replay".to_string(), path.display().to_string()];
+    let error = GameConfig::parse_args(args).unwrap_err();
+    assert!(error.contains("truncated"), "{error}");
+}
+
+#[test]
+fn replayed_run_dies_with_the_same_fireball_count() {
+    let last_run = |app: &App| {
+        app.world
+            .resource::<ReplayRecorder>()
+            .last_run
+            .clone()
+            .unwrap()
+    };
+    let mut app = in_game_app(deadly_config(11));
+    // Some input worth replaying
+    tap_key(&mut app, KeyCode::ArrowLeft, Key::ArrowLeft);
+    step_frames(&mut app, 30);
+    tap_key(&mut app, KeyCode::ArrowUp, Key::ArrowUp);
+    play_until_game_over(&mut app, MAX_RUN_FRAMES);
+    let n_balls = app.world.resource::<GameData>().n_balls;
+    assert!(n_balls > 0);
+    let replay = last_run(&app);
+
+    // As if the replay was attached to a bug report
+    let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+    let mut app = in_game_app(GameConfig {
+        replay: ReplayMode::Play(loaded),
+        ..deadly_config(0)
+    });
+    play_until_game_over(&mut app, MAX_RUN_FRAMES);
+    assert_eq!(app.world.resource::<GameData>().n_balls, n_balls);
+    // The player died on the same frame
+    assert_eq!(last_run(&app).frames.len(), replay.frames.len());
+}
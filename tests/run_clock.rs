@@ -0,0 +1,47 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{input::keyboard::Key, prelude::*};
+use common::{headless_app, state, tap_key};
+use dodge_fire_ball::{state::GameState, stats::RunClock, step_frames};
+
+fn clock(app: &App) -> Duration {
+    app.world.resource::<RunClock>().elapsed()
+}
+
+#[test]
+fn clock_only_runs_in_game() {
+    let mut app = headless_app();
+    step_frames(&mut app, 30);
+    assert_eq!(clock(&app), Duration::ZERO);
+
+    tap_key(&mut app, KeyCode::Enter, Key::Enter);
+    assert_eq!(state(&app), GameState::InGame);
+    let started = clock(&app);
+    step_frames(&mut app, 60);
+    let before_pause = clock(&app);
+    assert!(before_pause - started >= Duration::from_secs_f32(0.99));
+
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(state(&app), GameState::Paused);
+    let paused = clock(&app);
+    step_frames(&mut app, 120);
+    assert_eq!(clock(&app), paused);
+
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(state(&app), GameState::InGame);
+    step_frames(&mut app, 60);
+    let resumed = clock(&app);
+    assert!(resumed > paused);
+
+    // The clock stops with the run, while the explosion plays
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::GameOver);
+    step_frames(&mut app, 2);
+    assert_eq!(state(&app), GameState::GameOver);
+    let over = clock(&app);
+    step_frames(&mut app, 60);
+    assert_eq!(clock(&app), over);
+}
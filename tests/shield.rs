@@ -0,0 +1,38 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{hit_player, in_game_app, player, player_velocity, state};
+use dodge_fire_ball::{
+    pickup::PowerUps, player::Invulnerable, state::GameState, step_frames, tuning::GameTuning,
+    ui::GameData, GameConfig,
+};
+
+#[test]
+fn shield_absorbs_a_single_hit() {
+    let mut app = in_game_app(GameConfig {
+        tuning: GameTuning {
+            player_lives: 1,
+            invulnerability_time: 0.5,
+            fireball_spawn_time: 1000.0,
+            pickup_spawn_time: 1000.0,
+            ..default()
+        },
+        ..GameConfig::headless()
+    });
+    let player = player(&mut app);
+    app.world.resource_mut::<PowerUps>().shield = true;
+    let velocity = player_velocity(&mut app);
+
+    hit_player(&mut app);
+    assert_eq!(state(&app), GameState::InGame);
+    assert_eq!(app.world.resource::<GameData>().lives, 1);
+    assert!(!app.world.resource::<PowerUps>().shield);
+    // No knockback, but a moment to get away
+    assert_eq!(player_velocity(&mut app), velocity);
+    assert!(app.world.get::<Invulnerable>(player).is_some());
+
+    step_frames(&mut app, 40);
+    assert!(app.world.get::<Invulnerable>(player).is_none());
+    hit_player(&mut app);
+    assert_eq!(state(&app), GameState::GameOver);
+}
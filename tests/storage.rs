@@ -0,0 +1,83 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use dodge_fire_ball::{
+    build_game_app,
+    storage::{FileScoreStore, ScoreStorage, ScoreStore},
+    ui::GameData,
+    GameConfig,
+};
+
+#[test]
+fn file_store_round_trips_values() {
+    let dir = TempDir::new("file_store_round_trip");
+    let mut storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    storage
+        .set("best_score", &(42u64, "run".to_string()))
+        .unwrap();
+    assert_eq!(
+        storage.get::<(u64, String)>("best_score").unwrap(),
+        Some((42, "run".to_string()))
+    );
+
+    // A new store on the same directory sees what was saved
+    let storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    assert_eq!(
+        storage.get::<(u64, String)>("best_score").unwrap(),
+        Some((42, "run".to_string()))
+    );
+}
+
+#[test]
+fn file_store_overwrites_through_a_temporary_file() {
+    let dir = TempDir::new("file_store_overwrite");
+    let mut store = FileScoreStore::new(dir.path());
+    store.set("best_score", "1".to_string()).unwrap();
+    store.set("best_score", "2".to_string()).unwrap();
+    assert_eq!(store.get("best_score").unwrap(), Some("2".to_string()));
+
+    let files: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files, ["best_score.ron"]);
+}
+
+#[test]
+fn file_store_misses_unknown_keys() {
+    let dir = TempDir::new("file_store_missing");
+    // Nothing was written yet, not even the directory
+    let mut storage = ScoreStorage::new(FileScoreStore::new(dir.path()));
+    assert_eq!(storage.get::<u64>("best_score").unwrap(), None);
+
+    storage.set("best_time", &1u64).unwrap();
+    assert_eq!(storage.get::<u64>("best_score").unwrap(), None);
+}
+
+#[test]
+fn headless_apps_keep_records_in_memory() {
+    let mut app = build_game_app(GameConfig::headless());
+    app.world
+        .resource_mut::<ScoreStorage>()
+        .set("headless_probe", &7u64)
+        .unwrap();
+
+    let app = build_game_app(GameConfig::headless());
+    let storage = app.world.resource::<ScoreStorage>();
+    assert_eq!(storage.get::<u64>("headless_probe").unwrap(), None);
+}
+
+#[test]
+fn unsigned_best_score_of_older_versions_is_reset() {
+    let dir = TempDir::new("legacy_best_score");
+    let mut store = FileScoreStore::new(dir.path());
+    store.set("best_score", "1200".to_string()).unwrap();
+
+    let app = common::app_with(GameConfig {
+        score_dir: Some(dir.path().to_path_buf()),
+        ..GameConfig::headless()
+    });
+    assert_eq!(app.world.resource::<GameData>().record, 0);
+}